@@ -0,0 +1,168 @@
+use std::fs;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//how long the process group gets to exit after SIGTERM before it is killed
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+//A setup.background_cmd started by trest, it runs in its own process group so stopping it also stops everything it started, like cargo run does
+//The process group gets stopped when this is dropped, so it cant outlive trest
+pub struct BackgroundProcess {
+  child: Child,
+  pub log_file: PathBuf,
+  stopped: bool,
+}
+
+impl BackgroundProcess {
+  //stdout and stderr both go into log_file, which gets truncated first
+  pub fn spawn(cmd: &str, log_file: &Path) -> io::Result<BackgroundProcess> {
+    let log = fs::File::create(log_file)?;
+    let child = process::Command::new("sh")
+      .arg("-c")
+      .arg(cmd)
+      .stdin(Stdio::null())
+      .stdout(log.try_clone()?)
+      .stderr(log)
+      .process_group(0)
+      .spawn()?;
+
+    return Ok(BackgroundProcess {
+      child,
+      log_file: log_file.to_path_buf(),
+      stopped: false,
+    });
+  }
+
+  //Returns the exit status if the process already exited
+  pub fn exit_status(&mut self) -> Option<process::ExitStatus> {
+    return self.child.try_wait().ok().flatten();
+  }
+
+  //The process is the leader of its process group, so both ids are the same
+  pub fn process_group_id(&self) -> u32 {
+    return self.child.id();
+  }
+
+  //Sends SIGTERM to the process group and SIGKILL to whatever is left after the grace period
+  pub fn stop(&mut self) {
+    if self.stopped {
+      return;
+    }
+    self.stopped = true;
+
+    signal_process_group(self.child.id(), "TERM");
+    let started = Instant::now();
+    while self.exit_status().is_none() && started.elapsed() < STOP_GRACE_PERIOD {
+      thread::sleep(Duration::from_millis(20));
+    }
+    //children of the process might still be running even if the process itself exited
+    signal_process_group(self.child.id(), "KILL");
+    let _ = self.child.wait();
+  }
+}
+
+impl Drop for BackgroundProcess {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+//Stops a process group without owning its leader, used when trest gets interrupted while the BackgroundProcess belongs to another thread
+//The leader cant be reaped here and stays a zombie, so only processes that are still running count
+pub fn stop_process_group(process_group_id: u32) {
+  signal_process_group(process_group_id, "TERM");
+  let started = Instant::now();
+  while is_process_group_running(process_group_id) && started.elapsed() < STOP_GRACE_PERIOD {
+    thread::sleep(Duration::from_millis(20));
+  }
+  signal_process_group(process_group_id, "KILL");
+}
+
+fn signal_process_group(process_group_id: u32, signal: &str) {
+  let _ = process::Command::new("kill")
+    .arg(format!("-{signal}"))
+    .arg("--")
+    .arg(format!("-{process_group_id}"))
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+}
+
+//Without /proc there is no way to tell, so the group is waited on for the whole grace period
+fn is_process_group_running(process_group_id: u32) -> bool {
+  let Ok(entries) = fs::read_dir("/proc") else {
+    return true;
+  };
+  return entries
+    .filter_map(Result::ok)
+    .filter_map(|x| fs::read_to_string(x.path().join("stat")).ok())
+    .any(|stat| {
+      //state and process group id are the 1st and 3rd field after the command name
+      let fields: Vec<&str> = stat.rsplit_once(')').map(|x| x.1.split_whitespace().collect()).unwrap_or_default();
+      return fields.len() > 2 && fields[0] != "Z" && fields[2] == process_group_id.to_string();
+    });
+}
+
+//Used if setup.log_file isnt set, the process id and worker id keep runs of trest and test chains running at the same time apart
+pub fn default_log_file(config_name: &str, worker_id: usize) -> PathBuf {
+  let file_name: String = config_name.chars().map(|x| if x.is_ascii_alphanumeric() || x == '-' { x } else { '_' }).collect();
+  return std::env::temp_dir().join(format!("trest_{file_name}_{}_{worker_id}.log", process::id()));
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn spawn_logs_output_and_stop_kills_process_group() {
+    let log_file = std::env::temp_dir().join(format!("trest_background_process_test_{}.log", process::id()));
+    let pid_file = std::env::temp_dir().join(format!("trest_background_process_test_{}.pid", process::id()));
+    let mut background_process = BackgroundProcess::spawn(&format!("echo started; echo failing >&2; sleep 30 & echo $! > {}; wait", pid_file.display()), &log_file).unwrap();
+
+    let started = Instant::now();
+    while !fs::read_to_string(&pid_file).is_ok_and(|x| x.ends_with('\n')) && started.elapsed() < Duration::from_secs(5) {
+      thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(background_process.exit_status(), None);
+
+    background_process.stop();
+    assert!(background_process.exit_status().is_some());
+    let sleep_pid = fs::read_to_string(&pid_file).unwrap();
+    assert!(!is_running(sleep_pid.trim()));
+    assert_eq!(fs::read_to_string(&log_file).unwrap(), "started\nfailing\n");
+
+    let _ = fs::remove_file(log_file);
+    let _ = fs::remove_file(pid_file);
+  }
+
+  #[test]
+  fn stop_process_group_stops_group_it_doesnt_own() {
+    let log_file = std::env::temp_dir().join(format!("trest_background_process_group_test_{}.log", process::id()));
+    let mut background_process = BackgroundProcess::spawn("sleep 30 & sleep 30", &log_file).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert!(is_process_group_running(background_process.process_group_id()));
+
+    let started = Instant::now();
+    stop_process_group(background_process.process_group_id());
+    assert!(started.elapsed() < STOP_GRACE_PERIOD);
+    assert!(!is_process_group_running(background_process.process_group_id()));
+    background_process.stop();
+
+    let _ = fs::remove_file(log_file);
+  }
+
+  //zombies dont count, as they might never get reaped if trest runs as pid 1 in a container
+  fn is_running(pid: &str) -> bool {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).unwrap_or_default();
+    return !stat.is_empty() && !stat.rsplit_once(')').is_some_and(|x| x.1.trim_start().starts_with('Z'));
+  }
+
+  #[test]
+  fn default_log_file_replaces_special_characters() {
+    assert!(default_log_file("backend / postgres", 2).ends_with(format!("trest_backend___postgres_{}_2.log", process::id())));
+  }
+}
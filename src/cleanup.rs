@@ -0,0 +1,165 @@
+use std::{process, thread};
+use std::os::unix::process::CommandExt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::Config;
+use crate::output::{output, outputln};
+use crate::background_process::{self, BackgroundProcess};
+
+//exit code used when trest got interrupted by SIGINT or SIGTERM, like shells do for SIGINT
+const EXIT_CODE_INTERRUPTED: i32 = 130;
+
+//Everything that has to be cleaned up for setups that already ran, so the signal handler can clean up while the main thread is still busy
+static PENDING_CLEANUPS: Mutex<Vec<PendingCleanup>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+struct PendingCleanup {
+  id: usize,
+  cmd: String,
+  process_group_id: Option<u32>,
+  //set while Cleanup::run is cleaning up, the signal handler waits for it instead of running it again
+  running: bool,
+}
+
+//Owns the cleanup of a single setup from before the setup runs until run is called
+//If it gets dropped without run, for example because of a panic, the cleanup runs then
+pub struct Cleanup {
+  id: usize,
+  cmd: String,
+  background_process: Option<BackgroundProcess>,
+  done: bool,
+}
+
+impl Cleanup {
+  pub fn new(config: &Config) -> Cleanup {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    pending_cleanups().push(PendingCleanup { id, cmd: config.cleanup.cmd.clone(), process_group_id: None, running: false });
+    return Cleanup {
+      id,
+      cmd: config.cleanup.cmd.clone(),
+      background_process: None,
+      done: false,
+    };
+  }
+
+  pub fn set_background_process(&mut self, background_process: BackgroundProcess) {
+    for pending_cleanup in pending_cleanups().iter_mut().filter(|x| x.id == self.id) {
+      pending_cleanup.process_group_id = Some(background_process.process_group_id());
+    }
+    self.background_process = Some(background_process);
+  }
+
+  pub fn background_process(&mut self) -> Option<&mut BackgroundProcess> {
+    return self.background_process.as_mut();
+  }
+
+  //Stops the background process and runs the cleanup cmd, returns why the cleanup cmd failed if it did
+  //The cleanup stays registered until it finished, so an interrupt in the meantime waits for it instead of exiting halfway through
+  pub fn run(&mut self) -> Result<(), String> {
+    if self.done {
+      return Ok(());
+    }
+    self.done = true;
+    for pending_cleanup in pending_cleanups().iter_mut().filter(|x| x.id == self.id) {
+      pending_cleanup.running = true;
+    }
+
+    let result = self.stop_and_run_cmd();
+    pending_cleanups().retain(|x| x.id != self.id);
+    //the signal handler exits trest once the other cleanups ran, this thread must not start anything new until then
+    while INTERRUPTED.load(Ordering::Relaxed) {
+      thread::park();
+    }
+    return result;
+  }
+
+  fn stop_and_run_cmd(&mut self) -> Result<(), String> {
+    if self.background_process.is_none() && self.cmd.is_empty() {
+      return Ok(());
+    }
+    output!("cleaning up... ");
+    if self.background_process.is_some() {
+      self.background_process.as_mut().unwrap().stop();
+    }
+    let result = run_cmd(&self.cmd);
+    match &result {
+      Ok(()) => outputln!("cleanup completed"),
+      Err(e) => outputln!("\x1b[91m{e}\x1b[0m"),
+    }
+    return result;
+  }
+}
+
+impl Drop for Cleanup {
+  fn drop(&mut self) {
+    let _ = self.run();
+  }
+}
+
+//A panic while the lock is held must not keep the remaining cleanups from running
+fn pending_cleanups() -> std::sync::MutexGuard<'static, Vec<PendingCleanup>> {
+  return PENDING_CLEANUPS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+}
+
+//On SIGINT or SIGTERM all pending cleanups run before trest exits
+pub fn handle_termination_signals() {
+  let result = ctrlc::set_handler(|| {
+    println!("\n\x1b[93minterrupted, cleaning up before exiting\x1b[0m");
+    INTERRUPTED.store(true, Ordering::Relaxed);
+    //cleanups that already started finish on their own thread, exiting now would abandon them halfway through
+    let mut cleanups = pending_cleanups();
+    while cleanups.iter().any(|x| x.running) {
+      drop(cleanups);
+      thread::sleep(Duration::from_millis(50));
+      cleanups = pending_cleanups();
+    }
+    //the lock is held until trest exits, so the main thread blocks instead of starting another setup or running a cleanup twice
+    for cleanup in cleanups.iter() {
+      if cleanup.process_group_id.is_some() {
+        background_process::stop_process_group(cleanup.process_group_id.unwrap());
+      }
+      if let Err(e) = run_cmd(&cleanup.cmd) {
+        println!("\x1b[91m{e}\x1b[0m");
+      }
+    }
+    process::exit(EXIT_CODE_INTERRUPTED);
+  });
+
+  if let Err(e) = result {
+    println!("\x1b[93mfailed to set up handling of SIGINT and SIGTERM, interrupting trest wont run cleanups: {e}\x1b[0m");
+  }
+}
+
+fn run_cmd(cmd: &str) -> Result<(), String> {
+  if cmd.is_empty() {
+    return Ok(());
+  }
+
+  //in its own process group, so the SIGINT of a ctrl+c in the terminal doesnt stop the cleanup as well
+  let output = process::Command::new("sh")
+    .arg("-c")
+    .arg(cmd)
+    .process_group(0)
+    .output()
+    .map_err(|e| format!("failed to run cleanup cmd: {e}"))?;
+
+  if !output.status.success() {
+    return Err(format!("cleanup cmd failed with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+  }
+  return Ok(());
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn run_cmd_reports_failures() {
+    assert_eq!(run_cmd("true"), Ok(()));
+    assert_eq!(run_cmd(""), Ok(()));
+    assert_eq!(run_cmd("echo no such container >&2; exit 1"), Err("cleanup cmd failed with exit status: 1: no such container".to_string()));
+  }
+}
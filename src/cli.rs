@@ -0,0 +1,380 @@
+use std::fmt;
+use regex_lite::Regex;
+
+use crate::{util, ConfigFile, DEFAULT_WORKER_BASE_PORT};
+use crate::tags::{TagExpression, TagFilter};
+
+pub const HELP: &str = "trest runs tests of json rest apis, described in a config file
+
+Usage:
+  trest [run] <config file> [options]   run the tests
+  trest validate <config file>          check the config file without running anything
+  trest list <config file> [filters]    print the configs, test chains and tests that would run
+  trest init [config file]              write an example config file, defaults to trest.json
+  trest help                            print this help
+  trest version                         print the version
+
+Filters, each can be repeated and matches if any of its patterns match:
+  --config=<pattern>        only run configs whose name matches
+  --chain=<pattern>         only run test chains whose name matches
+  --test=<pattern>          only run tests whose name matches, test chains without matching tests are left out
+  --tags=<expression>       only run tests whose tags match, like --tags='smoke and not slow'
+  --exclude-tags=<expr>     dont run tests whose tags match
+  Patterns are globs where * matches anything and ? a single character, or regular expressions like /^login/
+  Tag expressions combine tags with and, or, not and parentheses. Tests left out by tags are reported as deselected
+
+Options of run:
+  --report-junit=<path>     write a JUnit XML report
+  --report-json=<path>      write a JSON report
+  --jobs=<n>                run up to n test chains at the same time, defaults to 1
+  --worker-base-port=<port> the {{worker_port}} of the first worker, defaults to 4000
+  --fail-fast               stop after the first failed test
+  --max-failures=<n>        stop after n failed tests
+  --request-timeout=<ms>    fail requests that take longer, for configs, tests and tasks without their own timeout_ms
+  --rerun-failed=<n>        send the request of a failed test up to n more times, for tests without their own retry
+  --update-snapshots        rewrite snapshots of body_matches_snapshot that dont match the response instead of failing
+
+Options can also be given as --option value. --config_to_run and --test_to_run still work like --config and --chain.
+";
+
+#[derive(Debug)]
+pub enum Command {
+  Run(RunArgs),
+  Validate(String),
+  List(String, Filters),
+  Init(String),
+  Help,
+  Version,
+}
+
+#[derive(Debug)]
+pub struct RunArgs {
+  pub config_path: String,
+  pub filters: Filters,
+  pub report_junit: Option<String>,
+  pub report_json: Option<String>,
+  //how many test chains run at the same time
+  pub jobs: usize,
+  //{{worker_port}} is this plus the id of the worker
+  pub worker_base_port: usize,
+  //--fail-fast is the same as 1
+  pub max_failures: Option<usize>,
+  //used by configs that dont set timeout_ms
+  pub request_timeout_ms: Option<u64>,
+  //how often failed tests without a retry are sent again
+  pub rerun_failed: Option<usize>,
+  pub update_snapshots: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Filters {
+  pub configs: Vec<Pattern>,
+  pub chains: Vec<Pattern>,
+  pub tests: Vec<Pattern>,
+  //unlike the patterns, tags dont remove tests but mark them as deselected
+  pub tags: TagFilter,
+}
+
+//A glob or a regular expression written as /regex/, globs have to match the whole name
+#[derive(Debug, Clone)]
+pub struct Pattern {
+  pub source: String,
+  regex: Regex,
+}
+
+const SUBCOMMANDS: [&str; 6] = ["run", "validate", "list", "init", "help", "version"];
+const FILTER_OPTIONS: [&str; 7] = ["--config", "--chain", "--test", "--tags", "--exclude-tags", "--config_to_run", "--test_to_run"];
+const RUN_OPTIONS: [&str; 7] = ["--report-junit", "--report-json", "--jobs", "--worker-base-port", "--max-failures", "--request-timeout", "--rerun-failed"];
+const RUN_FLAGS: [&str; 2] = ["--fail-fast", "--update-snapshots"];
+const MAX_PORT: usize = 65535;
+
+//args dont contain the path of the binary
+//A first argument that isnt a subcommand is the config file of run, so trest config.json keeps working
+pub fn parse(args: &[String]) -> Result<Command, String> {
+  if args.iter().any(|x| x == "--help" || x == "-h") {
+    return Ok(Command::Help);
+  }
+  if args.iter().any(|x| x == "--version" || x == "-V") {
+    return Ok(Command::Version);
+  }
+
+  let (subcommand, rest) = match args.first() {
+    None => return Ok(Command::Help),
+    Some(first) if SUBCOMMANDS.contains(&first.as_str()) => (first.as_str(), &args[1..]),
+    Some(_) => ("run", args),
+  };
+
+  let (positionals, options) = split_options(subcommand, rest)?;
+  let config_path = || positionals.first().cloned().ok_or(format!("{subcommand} needs the path of a config file"));
+  if positionals.len() > 1 {
+    return Err(format!("unexpected argument \"{}\"", positionals[1]));
+  }
+
+  return match subcommand {
+    "help" => Ok(Command::Help),
+    "version" => Ok(Command::Version),
+    "init" => Ok(Command::Init(positionals.first().cloned().unwrap_or(String::from("trest.json")))),
+    "validate" => Ok(Command::Validate(config_path()?)),
+    "list" => Ok(Command::List(config_path()?, get_filters(&options)?)),
+    _ => {
+      let max_failures = if options.iter().any(|(name, _)| name == "--fail-fast") { Some(1) } else { get_number(&options, "--max-failures", 1)? };
+      let jobs = get_number(&options, "--jobs", 1)?.unwrap_or(1);
+      let worker_base_port = get_number(&options, "--worker-base-port", 1)?.unwrap_or(DEFAULT_WORKER_BASE_PORT);
+      //the last worker gets the highest port
+      if worker_base_port.saturating_add(jobs - 1) > MAX_PORT {
+        return Err(format!("--worker-base-port={worker_base_port} with --jobs={jobs} gives worker ports above {MAX_PORT}"));
+      }
+      Ok(Command::Run(RunArgs {
+        config_path: config_path()?,
+        filters: get_filters(&options)?,
+        report_junit: get_value(&options, "--report-junit"),
+        report_json: get_value(&options, "--report-json"),
+        jobs,
+        worker_base_port,
+        max_failures,
+        request_timeout_ms: get_number(&options, "--request-timeout", 1)?.map(|x| x as u64),
+        rerun_failed: get_number(&options, "--rerun-failed", 1)?,
+        update_snapshots: options.iter().any(|(name, _)| name == "--update-snapshots"),
+      }))
+    },
+  };
+}
+
+//name and value of every option in the order they were given, flags have an empty value
+type Options = Vec<(String, String)>;
+
+//Returns the positional arguments and the options
+fn split_options(subcommand: &str, args: &[String]) -> Result<(Vec<String>, Options), String> {
+  let allowed_options: Vec<&str> = match subcommand {
+    "run" => FILTER_OPTIONS.iter().chain(RUN_OPTIONS.iter()).copied().collect(),
+    "list" => FILTER_OPTIONS.to_vec(),
+    _ => Vec::new(),
+  };
+  let allowed_flags: &[&str] = if subcommand == "run" { &RUN_FLAGS } else { &[] };
+
+  let mut positionals: Vec<String> = Vec::new();
+  let mut options: Options = Vec::new();
+  let mut args = args.iter().peekable();
+
+  while let Some(arg) = args.next() {
+    if !arg.starts_with("--") {
+      positionals.push(arg.clone());
+      continue;
+    }
+
+    let (name, value) = match arg.split_once('=') {
+      Some((name, value)) => (name, Some(value.to_string())),
+      None => (arg.as_str(), None),
+    };
+    if allowed_flags.contains(&name) && value.is_none() {
+      options.push((name.to_string(), String::new()));
+    } else if allowed_flags.contains(&name) {
+      return Err(format!("{name} doesnt take a value"));
+    } else if allowed_options.contains(&name) {
+      //an option following it is never taken as its value, values starting with -- can be given as --name=value
+      let value = value.or_else(|| args.next_if(|x| !x.starts_with("--")).cloned()).filter(|x| !x.is_empty()).ok_or(format!("{name} needs a value"))?;
+      options.push((name.to_string(), value));
+    } else {
+      let known: Vec<&str> = allowed_options.iter().chain(allowed_flags.iter()).copied().collect();
+      return Err(match known.iter().find(|x| util::edit_distance(x, name) <= 2) {
+        Some(suggestion) => format!("unknown option {name} for {subcommand}, did you mean {suggestion}?"),
+        None => format!("unknown option {name} for {subcommand}"),
+      });
+    }
+  }
+
+  return Ok((positionals, options));
+}
+
+//The last value wins if an option that isnt a filter is given more than once
+fn get_value(options: &[(String, String)], name: &str) -> Option<String> {
+  return options.iter().rev().find(|x| x.0 == name).map(|x| x.1.clone());
+}
+
+fn get_number(options: &[(String, String)], name: &str, min: usize) -> Result<Option<usize>, String> {
+  let Some(value) = get_value(options, name) else {
+    return Ok(None);
+  };
+  return value.parse::<usize>().ok().filter(|x| *x >= min).map(Some).ok_or(format!("invalid value {value} for {name}, expected a number of at least {min}"));
+}
+
+fn get_filters(options: &[(String, String)]) -> Result<Filters, String> {
+  let patterns = |names: &[&str]| -> Result<Vec<Pattern>, String> {
+    return options.iter().filter(|x| names.contains(&x.0.as_str())).map(|x| Pattern::parse(&x.1)).collect();
+  };
+
+  let tag_expressions = |name: &str| -> Result<Vec<TagExpression>, String> {
+    return options.iter().filter(|x| x.0 == name).map(|x| TagExpression::parse(&x.1).map_err(|e| format!("invalid value for {name}: {e}"))).collect();
+  };
+
+  return Ok(Filters {
+    configs: patterns(&["--config", "--config_to_run"])?,
+    chains: patterns(&["--chain", "--test_to_run"])?,
+    tests: patterns(&["--test"])?,
+    tags: TagFilter {
+      include: tag_expressions("--tags")?,
+      exclude: tag_expressions("--exclude-tags")?,
+    },
+  });
+}
+
+impl Pattern {
+  pub fn parse(source: &str) -> Result<Pattern, String> {
+    if let Some(regex) = source.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+      let regex = Regex::new(regex).map_err(|e| format!("invalid regular expression {source}: {e}"))?;
+      return Ok(Pattern { source: source.to_string(), regex });
+    }
+
+    let glob: String = source.chars().map(|x| match x {
+      '*' => String::from(".*"),
+      '?' => String::from("."),
+      x => regex_lite::escape(&x.to_string()),
+    }).collect();
+    let regex = Regex::new(&format!("^{glob}$")).map_err(|e| format!("invalid pattern {source}: {e}"))?;
+    return Ok(Pattern { source: source.to_string(), regex });
+  }
+
+  pub fn matches(&self, name: &str) -> bool {
+    return self.regex.is_match(name);
+  }
+}
+
+//Prints the filters the way they are given as arguments
+impl fmt::Display for Filters {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let tags = [("--tags", &self.tags.include), ("--exclude-tags", &self.tags.exclude)]
+      .iter()
+      .flat_map(|(name, expressions)| expressions.iter().map(move |x| format!("{name}=\"{x}\"")))
+      .collect::<Vec<String>>();
+    let filters: Vec<String> = [("--config", &self.configs), ("--chain", &self.chains), ("--test", &self.tests)]
+      .iter()
+      .flat_map(|(name, patterns)| patterns.iter().map(move |x| format!("{name}={}", x.source)))
+      .chain(tags)
+      .collect();
+    return write!(f, "{}", filters.join(" "));
+  }
+}
+
+impl Filters {
+  pub fn is_empty(&self) -> bool {
+    return self.configs.is_empty() && self.chains.is_empty() && self.tests.is_empty() && self.tags.is_empty();
+  }
+
+  //Removes everything the filters dont match, test chains that have no tests left after filtering tests are removed too
+  pub fn apply(&self, config_file: &mut ConfigFile) {
+    let matches = |patterns: &[Pattern], name: &str| patterns.is_empty() || patterns.iter().any(|x| x.matches(name));
+
+    config_file.configs = config_file.configs.iter().filter(|x| matches(&self.configs, &x.name)).cloned().collect();
+    config_file.tests = config_file.tests
+      .iter()
+      .filter(|x| matches(&self.chains, &x.name))
+      .map(|test_chain| {
+        let mut test_chain = test_chain.clone();
+        test_chain.tests = test_chain.tests.iter().filter(|x| matches(&self.tests, &x.name)).cloned().collect();
+        return test_chain;
+      })
+      .filter(|x| !x.tests.is_empty() || self.tests.is_empty())
+      .collect();
+  }
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  fn parse_str(args: &str) -> Result<Command, String> {
+    return parse(&args.split_whitespace().map(String::from).collect::<Vec<String>>());
+  }
+
+  fn sources(patterns: &[Pattern]) -> Vec<&str> {
+    return patterns.iter().map(|x| x.source.as_str()).collect();
+  }
+
+  #[test]
+  fn parse_run_with_options() {
+    let Ok(Command::Run(args)) = parse_str("run config.json --config=backend --config /^staging/ --chain=currency* --jobs 4 --fail-fast --report-json=report.json") else { panic!("expected run") };
+
+    assert_eq!(args.config_path, "config.json");
+    assert_eq!(sources(&args.filters.configs), vec!["backend", "/^staging/"]);
+    assert_eq!(sources(&args.filters.chains), vec!["currency*"]);
+    assert_eq!(args.jobs, 4);
+    assert_eq!(args.worker_base_port, DEFAULT_WORKER_BASE_PORT);
+    assert_eq!(args.max_failures, Some(1));
+    assert_eq!(args.report_json, Some(String::from("report.json")));
+    assert_eq!(args.report_junit, None);
+    assert_eq!(args.request_timeout_ms, None);
+    assert!(!args.update_snapshots);
+    assert_eq!(args.filters.to_string(), "--config=backend --config=/^staging/ --chain=currency*");
+  }
+
+  #[test]
+  fn parse_old_syntax_as_run() {
+    let Ok(Command::Run(args)) = parse_str("config.json --config_to_run=backend --test_to_run=currency --max-failures=3 --request-timeout=5000 --rerun-failed 2") else { panic!("expected run") };
+
+    assert_eq!(args.config_path, "config.json");
+    assert_eq!(sources(&args.filters.configs), vec!["backend"]);
+    assert_eq!(sources(&args.filters.chains), vec!["currency"]);
+    assert_eq!(args.max_failures, Some(3));
+    assert_eq!(args.request_timeout_ms, Some(5000));
+    assert_eq!(args.rerun_failed, Some(2));
+    assert!(matches!(parse_str("run --update-snapshots config.json"), Ok(Command::Run(x)) if x.update_snapshots && x.config_path == "config.json"));
+  }
+
+  #[test]
+  fn parse_other_subcommands() {
+    assert!(matches!(parse_str("validate config.json"), Ok(Command::Validate(x)) if x == "config.json"));
+    assert!(matches!(parse_str("list config.json --test=*works"), Ok(Command::List(x, filters)) if x == "config.json" && sources(&filters.tests) == vec!["*works"]));
+    assert!(matches!(parse_str("list config.json --tags=smoke --exclude-tags=slow"), Ok(Command::List(_, filters)) if filters.tags.include.len() == 1 && filters.tags.exclude.len() == 1));
+    assert!(matches!(parse_str("init"), Ok(Command::Init(x)) if x == "trest.json"));
+    assert!(matches!(parse_str(""), Ok(Command::Help)));
+    assert!(matches!(parse_str("run config.json --help"), Ok(Command::Help)));
+    assert!(matches!(parse_str("-V"), Ok(Command::Version)));
+  }
+
+  #[test]
+  fn parse_reports_errors() {
+    assert_eq!(parse_str("run config.json --jbos=2").unwrap_err(), "unknown option --jbos for run, did you mean --jobs?");
+    assert_eq!(parse_str("validate config.json --jobs=2").unwrap_err(), "unknown option --jobs for validate");
+    assert_eq!(parse_str("run config.json --jobs=0").unwrap_err(), "invalid value 0 for --jobs, expected a number of at least 1");
+    assert_eq!(parse_str("run config.json --report-json").unwrap_err(), "--report-json needs a value");
+    assert_eq!(parse_str("run config.json --report-junit --fail-fast").unwrap_err(), "--report-junit needs a value");
+    assert_eq!(parse_str("run config.json --fail-fast=true").unwrap_err(), "--fail-fast doesnt take a value");
+    assert_eq!(parse_str("run config.json --worker-base-port=65535 --jobs=2").unwrap_err(), "--worker-base-port=65535 with --jobs=2 gives worker ports above 65535");
+    assert_eq!(parse_str("run config.json --worker-base-port=70000").unwrap_err(), "--worker-base-port=70000 with --jobs=1 gives worker ports above 65535");
+    assert!(parse_str("run config.json --worker-base-port=65534 --jobs=2").is_ok());
+    assert_eq!(parse_str("run").unwrap_err(), "run needs the path of a config file");
+    assert_eq!(parse_str("run a.json b.json").unwrap_err(), "unexpected argument \"b.json\"");
+    assert_eq!(parse_str("run a.json --tags=smoke,slow").unwrap_err(), "invalid value for --tags: unexpected \",\" in tag expression \"smoke,slow\"");
+    assert_eq!(parse_str("run a.json --test=/(/").unwrap_err().split(':').next(), Some("invalid regular expression /(/"));
+  }
+
+  #[test]
+  fn filters_remove_unmatched_configs_chains_and_tests() {
+    let sample = crate::parse_config_file(&jzon::parse(&std::fs::read_to_string("./test/sample_parsed.json").unwrap()).unwrap());
+
+    let mut config_file = sample.clone();
+    let Ok(Command::List(_, filters)) = parse_str("list config.json --chain=recipients* --test=/^(post|updating)/") else { panic!("expected list") };
+    filters.apply(&mut config_file);
+    assert_eq!(config_file.configs.len(), 1);
+    assert_eq!(config_file.tests.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["recipients", "recipients_with_defaults"]);
+    assert_eq!(config_file.tests.iter().next().unwrap().tests.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["post of new recipient returns 200", "updating name of recipient returns 200"]);
+
+    let mut config_file = sample.clone();
+    let Ok(Command::List(_, filters)) = parse_str("list config.json --config=frontend --test=*currencies*") else { panic!("expected list") };
+    filters.apply(&mut config_file);
+    assert!(config_file.configs.is_empty());
+    assert_eq!(config_file.tests.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["currency"]);
+  }
+
+  #[test]
+  fn pattern_matches_globs_and_regexes() {
+    let glob = Pattern::parse("retrieval of * works").unwrap();
+    assert!(glob.matches("retrieval of all currencies works"));
+    assert!(!glob.matches("retrieval of all currencies works too"));
+    assert!(Pattern::parse("currenc?").unwrap().matches("currency"));
+    assert!(Pattern::parse("a.b").unwrap().matches("a.b"));
+    assert!(!Pattern::parse("a.b").unwrap().matches("axb"));
+    assert!(Pattern::parse("/^login/").unwrap().matches("login as admin"));
+    assert!(!Pattern::parse("/^login/").unwrap().matches("admin login"));
+  }
+}
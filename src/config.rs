@@ -1,115 +1,639 @@
-use std::process;
+use std::{process, thread};
+use std::any::Any;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use crate::{Config, ConfigFile, test};
+use std::time::{Duration, Instant};
+use linked_hash_map::LinkedHashMap;
+use crate::{Config, ConfigFile, Retry, SetupFinishedCondition, Test, TestChain, test, http_request, variables, readiness, output, snapshot};
+use crate::readiness::SetupContext;
+use crate::cleanup::Cleanup;
+use crate::background_process::{self, BackgroundProcess};
+use crate::variables::{Variables, CaptureResponse};
+use crate::report::{ConfigResult, TestChainResult, TestResult, TestStatus};
+use crate::test::TestFailure;
+use crate::output::{output, outputln};
+use crate::tags::TagFilter;
 
-pub fn run(config: &Config, config_file: &ConfigFile) -> bool {
-  println!("Running config \x1b[96m{}\x1b[0m: \x1b[96m{}\x1b[0m", config.name, config.description);
-  
-  let mut test_outcomes: Vec<Option<String>> = Vec::new();
+//a single check of the finished_condition never blocks longer than this, so a server that accepts connections but never answers cant hang the setup
+const MAX_CHECK_DURATION: Duration = Duration::from_secs(10);
+const MIN_CHECK_DURATION: Duration = Duration::from_millis(100);
+//how many tests the summary of the slowest tests at the end of a test chain lists
+const SLOWEST_TESTS_SHOWN: usize = 3;
 
-  for test_chain in config_file.tests.iter() {
-    println!("\n--------------\nrunning test chain \x1b[96m{}\x1b[0m", test_chain.name);
-    run_setup(config);
-  
-    let mut test_responses: HashMap<String, jzon::JsonValue> = HashMap::new();
-
-    let mut test_chain_outcomes: Vec<Option<String>> = test_chain.tests
-      .iter()
-      .map(|test| {
-        if test.skip {
-          println!("skipping test \x1b[96m{}\x1b[0m", test.name);
-          return None;
-        }
-        print!("running test \x1b[96m{}\x1b[0m: ", test.name);
-        let response = test::run_test_http_request(test.clone(), config, config_file, test_responses.clone());
-        let response_status_code = response.status();
-        let response_content_type = String::from(response.content_type());
-        let response_body = response.into_string().unwrap();
-
-        if response_content_type == "application/json" {
-          test_responses.insert(test.name.clone(), jzon::parse(response_body.as_str()).unwrap_or(jzon::Null));
+//The worker a test chain runs on, setup and cleanup can use its id and port as {{worker_id}} and {{worker_port}}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Worker {
+  pub id: usize,
+  pub port: usize,
+}
+
+//How the test chains of all configs are run, set through cli arguments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunOptions {
+  //how many test chains run at the same time
+  pub jobs: usize,
+  //{{worker_port}} is this plus the id of the worker
+  pub worker_base_port: usize,
+  //once this many tests failed, all remaining tests are skipped, --fail-fast is the same as 1
+  pub max_failures: Option<usize>,
+  //tests that dont match --tags or match --exclude-tags are reported as deselected instead of running
+  pub tag_filter: TagFilter,
+  //where the snapshots of body_matches_snapshot are kept, next to the config file
+  pub snapshot_dir: PathBuf,
+  //rewrite snapshots that dont match the response instead of failing, set by --update-snapshots
+  pub update_snapshots: bool,
+}
+
+//Everything of a response that a test checks or captures from
+struct TestResponse {
+  status_code: u16,
+  content_type: String,
+  headers: LinkedHashMap<String, String>,
+  cookies: LinkedHashMap<String, String>,
+  url: String,
+  body: String,
+  //from sending the request until the whole body was read
+  duration: Duration,
+}
+
+//Counts the failed tests of all test chains, including those running at the same time on other workers
+struct FailureCount {
+  max_failures: Option<usize>,
+  failures: AtomicUsize,
+}
+
+impl FailureCount {
+  fn add(&self) {
+    self.failures.fetch_add(1, Ordering::Relaxed);
+  }
+
+  //Returns why the remaining tests are skipped if the limit of failed tests is reached
+  fn stop_reason(&self) -> Option<String> {
+    let failures = self.failures.load(Ordering::Relaxed);
+    return self.max_failures.filter(|x| failures >= *x).map(|_| format!("stopped after {failures} failed test{}", if failures == 1 { "" } else { "s" }));
+  }
+}
+
+//Runs the test chains of all configs on options.jobs workers, with a single job everything runs in order on the current thread
+//Also returns how long the whole run took
+pub fn run_all(config_file: &ConfigFile, options: &RunOptions) -> (Vec<ConfigResult>, Duration) {
+  let run_started = Instant::now();
+  let failure_count = &FailureCount { max_failures: options.max_failures, failures: AtomicUsize::new(0) };
+  let (jobs, worker_base_port) = (options.jobs, options.worker_base_port);
+  if jobs <= 1 {
+    let worker = Worker { id: 0, port: worker_base_port };
+    let config_results = config_file.configs.iter().map(|config| run(config, config_file, worker, options, failure_count)).collect();
+    return (config_results, run_started.elapsed());
+  }
+
+  let configs: Vec<&Config> = config_file.configs.iter().collect();
+  let units: Vec<(usize, &TestChain)> = (0..configs.len()).flat_map(|x| config_file.tests.iter().map(move |test_chain| (x, test_chain))).collect();
+  let setup_failed: Vec<AtomicBool> = configs.iter().map(|_| AtomicBool::new(false)).collect();
+  let next_unit = AtomicUsize::new(0);
+  let results: Mutex<Vec<Option<TestChainResult>>> = Mutex::new(vec![None; units.len()]);
+
+  thread::scope(|scope| {
+    for id in 0..jobs.min(units.len()) {
+      let worker = Worker { id, port: worker_base_port + id };
+      let (units, configs, setup_failed, next_unit, results) = (&units, &configs, &setup_failed, &next_unit, &results);
+      scope.spawn(move || loop {
+        let index = next_unit.fetch_add(1, Ordering::Relaxed);
+        let Some((config_index, test_chain)) = units.get(index) else { break };
+        let config = configs[*config_index];
+        //like without --jobs, test chains of a config whose setup failed dont start anymore
+        if setup_failed[*config_index].load(Ordering::Relaxed) {
+          let (test_chain_result, output) = output::buffered(|| setup_failed_test_chain_result(config, test_chain, worker));
+          print!("{output}");
+          results.lock().unwrap()[index] = Some(test_chain_result);
+          continue;
         }
 
-        let result = test::check_test_result(test, response_status_code, &response_content_type, &response_body, test_responses.clone());
+        let (test_chain_result, output) = output::buffered(|| {
+          outputln!("\n--------------\nrunning test chain \x1b[96m{}\x1b[0m of config \x1b[96m{}\x1b[0m on worker {}", test_chain.name, config.name, worker.id);
+          return run_test_chain(config, config_file, test_chain, worker, options, failure_count);
+        });
+        print!("{output}");
+
+        if test_chain_result.setup_error.is_some() {
+          setup_failed[*config_index].store(true, Ordering::Relaxed);
+        }
+        results.lock().unwrap()[index] = Some(test_chain_result);
+      });
+    }
+  });
 
-        return match result {
-          test::TestResults::Passed => None,
-          test::TestResults::Failed(actual_outcome) => Some(test::stringify_test_outcome(&actual_outcome, &test.expected_outcome, &response_body, &test_chain.name, &test.name)),
-        };
-      })
-      .collect();
+  let duration = run_started.elapsed();
+  let mut results = results.into_inner().unwrap().into_iter();
+  let config_results = configs.iter().map(|config| {
+    let config_result = ConfigResult {
+      name: config.name.clone(),
+      test_chains: results.by_ref().take(config_file.tests.len()).flatten().collect(),
+    };
+    print_summary(config, &config_result);
+    return config_result;
+  }).collect();
+  return (config_results, duration);
+}
 
-    test_outcomes.append(&mut test_chain_outcomes);
+fn run(config: &Config, config_file: &ConfigFile, worker: Worker, options: &RunOptions, failure_count: &FailureCount) -> ConfigResult {
+  outputln!("Running config \x1b[96m{}\x1b[0m: \x1b[96m{}\x1b[0m", config.name, config.description);
   
-    if !config.cleanup.cmd.is_empty() {
-      run_cleanup(&config.cleanup.cmd);
+  let mut config_result = ConfigResult {
+    name: config.name.clone(),
+    test_chains: Vec::new(),
+  };
+
+  let mut setup_failed = false;
+  for test_chain in &config_file.tests {
+    //the remaining test chains would only wait for the same setup again
+    if setup_failed {
+      config_result.test_chains.push(setup_failed_test_chain_result(config, test_chain, worker));
+      continue;
     }
+    outputln!("\n--------------\nrunning test chain \x1b[96m{}\x1b[0m", test_chain.name);
+    let test_chain_result = run_test_chain(config, config_file, test_chain, worker, options, failure_count);
+    setup_failed = test_chain_result.setup_error.is_some();
+    config_result.test_chains.push(test_chain_result);
+  }
+
+  print_summary(config, &config_result);
+  return config_result;
+}
+
+//A test chain that doesnt run because the setup of its config already failed for an earlier test chain, its tests are reported as skipped
+fn setup_failed_test_chain_result(config: &Config, test_chain: &TestChain, worker: Worker) -> TestChainResult {
+  let config = &variables::resolve_config(config, &variables::worker_variables(worker.id, worker.port));
+  outputln!("skipping test chain \x1b[96m{}\x1b[0m: setup failed", test_chain.name);
+  return TestChainResult {
+    name: test_chain.name.clone(),
+    duration: Duration::ZERO,
+    tests: test_chain.tests.iter().map(|test| skipped_test_result(test, config, String::from("setup failed"))).collect(),
+    setup_error: None,
+    cleanup_error: None,
+  };
+}
+
+fn run_test_chain(config: &Config, config_file: &ConfigFile, test_chain: &TestChain, worker: Worker, options: &RunOptions, failure_count: &FailureCount) -> TestChainResult {
+  let config = &variables::resolve_config(config, &variables::worker_variables(worker.id, worker.port));
+  let test_chain_started = Instant::now();
+  let deselect_reasons: Vec<Option<String>> = test_chain.tests.iter().map(|x| options.tag_filter.deselect_reason(&x.tags)).collect();
+
+  //a setup for a test chain whose tests are all deselected would only slow the run down
+  if !test_chain.tests.is_empty() && deselect_reasons.iter().all(Option::is_some) {
+    outputln!("skipping test chain \x1b[96m{}\x1b[0m: all of its tests are deselected by tags", test_chain.name);
+    return TestChainResult {
+      name: test_chain.name.clone(),
+      duration: Duration::ZERO,
+      tests: test_chain.tests.iter().zip(deselect_reasons).map(|(test, reason)| deselected_test_result(test, config, reason.unwrap())).collect(),
+      setup_error: None,
+      cleanup_error: None,
+    };
   }
 
-  let passed_tests = test_outcomes.iter().filter(|x| x.is_none()).collect::<Vec<_>>().len();
-  let total_tests = test_outcomes.len();
+  //there is no point in setting up for tests that would all be skipped
+  if let Some(stop_reason) = failure_count.stop_reason() {
+    outputln!("skipping test chain \x1b[96m{}\x1b[0m: {stop_reason}", test_chain.name);
+    return TestChainResult {
+      name: test_chain.name.clone(),
+      duration: Duration::ZERO,
+      tests: test_chain.tests.iter().map(|test| skipped_test_result(test, config, stop_reason.clone())).collect(),
+      setup_error: None,
+      cleanup_error: None,
+    };
+  }
+
+  //the cleanup is registered before the setup runs, so it also runs if the setup fails, panics or trest gets interrupted
+  let mut cleanup = Cleanup::new(config);
+  let setup_result = panic::catch_unwind(AssertUnwindSafe(|| run_setup(config, worker, &mut cleanup)))
+    .unwrap_or_else(|e| Err(format!("setup panicked: {}", panic_message(&*e))));
+  if let Err(setup_error) = setup_result {
+    outputln!("\x1b[91m{setup_error}\x1b[0m");
+    return TestChainResult {
+      name: test_chain.name.clone(),
+      duration: test_chain_started.elapsed(),
+      tests: test_chain.tests.iter().map(|test| skipped_test_result(test, config, String::from("setup failed"))).collect(),
+      setup_error: Some(setup_error),
+      cleanup_error: cleanup.run().err(),
+    };
+  }
+
+  let mut test_responses: HashMap<String, jzon::JsonValue> = HashMap::new();
+  let mut variables: Variables = HashMap::new();
+  let mut first_failed_test: Option<&str> = None;
+
+  let test_results: Vec<TestResult> = test_chain.tests
+    .iter()
+    .zip(deselect_reasons)
+    .map(|(test, deselect_reason)| {
+      if let Some(deselect_reason) = deselect_reason {
+        outputln!("skipping test \x1b[96m{}\x1b[0m: {deselect_reason}", test.name);
+        return deselected_test_result(test, config, deselect_reason);
+      }
+
+      let stop_reason = failure_count.stop_reason()
+        .or(first_failed_test.filter(|_| test_chain.stop_on_failure).map(|x| format!("test chain stopped after {x} failed")));
+      if let Some(stop_reason) = stop_reason {
+        outputln!("skipping test \x1b[96m{}\x1b[0m: {stop_reason}", test.name);
+        return skipped_test_result(test, config, stop_reason);
+      }
+
+      //a panicking test fails on its own instead of taking down the remaining tests and the cleanup
+      let test_result = panic::catch_unwind(AssertUnwindSafe(|| run_test(test, &test_chain.name, config, config_file, options, &mut test_responses, &mut variables)))
+        .unwrap_or_else(|e| panicked_test_result(test, config, &*e));
+      if matches!(test_result.status, TestStatus::Failed(_)) {
+        failure_count.add();
+        first_failed_test = first_failed_test.or(Some(&test.name));
+      }
+      return test_result;
+    })
+    .collect();
+
+  print_slowest_tests(&test_results);
+  let cleanup_error = cleanup.run().err();
 
-  println!(
-    "\nConfig \x1b[96m{}\x1b[0m passed {} of {} tests",
+  return TestChainResult {
+    name: test_chain.name.clone(),
+    duration: test_chain_started.elapsed(),
+    tests: test_results,
+    setup_error: None,
+    cleanup_error,
+  };
+}
+
+fn print_summary(config: &Config, config_result: &ConfigResult) {
+  let test_results = config_result.test_chains.iter().flat_map(|x| x.tests.iter());
+  let total_tests = test_results.clone().count();
+  let passed_tests = test_results.clone().filter(|x| x.status == TestStatus::Passed).count();
+  let flaky_tests = test_results.clone().filter(|x| x.is_flaky()).count();
+  let skipped_tests = test_results.clone().filter(|x| matches!(x.status, TestStatus::Skipped(_))).count();
+  let deselected_tests = test_results.clone().filter(|x| matches!(x.status, TestStatus::Deselected(_))).count();
+
+  outputln!(
+    "\nConfig \x1b[96m{}\x1b[0m passed {} of {} tests{}{}{}",
     config.name,
     passed_tests,
-    total_tests
+    total_tests,
+    if flaky_tests > 0 { format!(", {flaky_tests} flaky") } else { String::new() },
+    if skipped_tests > 0 { format!(", {skipped_tests} skipped") } else { String::new() },
+    if deselected_tests > 0 { format!(", {deselected_tests} deselected") } else { String::new() },
   );
 
-  if passed_tests == total_tests {
-    return false;
+  for test_chain in &config_result.test_chains {
+    for test_result in &test_chain.tests {
+      if let TestStatus::Failed(failure) = &test_result.status {
+        outputln!("{}", test::stringify_test_outcome(failure, &test_result.expected_outcome, &test_chain.name, &test_result.name));
+      }
+    }
   }
 
-  for x in test_outcomes {
-    match x {
-      None => continue,
-      Some(x) => println!("{x}"),
+  for test_chain in &config_result.test_chains {
+    for test_result in test_chain.tests.iter().filter(|x| x.is_flaky()) {
+      outputln!("Test \x1b[96m{}\x1b[0m: \x1b[96m{}\x1b[0m \x1b[93mis flaky, it only passed on attempt {}\x1b[0m", test_chain.name, test_result.name, test_result.attempts);
     }
   }
-  return true;
+  for test_chain in config_result.test_chains.iter().filter(|x| x.setup_error.is_some()) {
+    outputln!("Test chain \x1b[96m{}\x1b[0m \x1b[91mdidnt run: {}\x1b[0m", test_chain.name, test_chain.setup_error.as_ref().unwrap());
+  }
+  for test_chain in config_result.test_chains.iter().filter(|x| x.cleanup_error.is_some()) {
+    outputln!("Test chain \x1b[96m{}\x1b[0m \x1b[91mwasnt cleaned up: {}\x1b[0m", test_chain.name, test_chain.cleanup_error.as_ref().unwrap());
+  }
 }
 
-fn run_setup(config: &Config) {
-  print!("setting up... ");
+//Lists the tests of a test chain whose responses took the longest, so performance regressions show up in the output
+fn print_slowest_tests(test_results: &[TestResult]) {
+  let mut timed_tests: Vec<&TestResult> = test_results.iter().filter(|x| x.latency.is_some()).collect();
+  if timed_tests.is_empty() {
+    return;
+  }
+  timed_tests.sort_by_key(|x| std::cmp::Reverse(x.latency));
+
+  outputln!("slowest tests:");
+  for test_result in timed_tests.iter().take(SLOWEST_TESTS_SHOWN) {
+    outputln!("  {:>6}ms \x1b[96m{}\x1b[0m", test_result.latency.unwrap().as_millis(), test_result.name);
+  }
+}
+
+fn skipped_test_result(test: &Test, config: &Config, reason: String) -> TestResult {
+  return TestResult {
+    name: test.name.clone(),
+    status: TestStatus::Skipped(reason),
+    duration: Duration::ZERO,
+    method: test.method.clone(),
+    url: format!("{}{}", config.api_hostname, test.endpoint),
+    response_status_code: None,
+    expected_outcome: test.expected_outcome.clone(),
+    attempts: 0,
+    latency: None,
+  };
+}
+
+fn deselected_test_result(test: &Test, config: &Config, reason: String) -> TestResult {
+  return TestResult {
+    status: TestStatus::Deselected(reason),
+    ..skipped_test_result(test, config, String::new())
+  };
+}
+
+fn panicked_test_result(test: &Test, config: &Config, panic: &(dyn Any + Send)) -> TestResult {
+  return errored_test_result(test, config, &format!("test panicked: {}", panic_message(panic)));
+}
+
+//A test that failed without a response to check, like one that timed out or panicked
+fn errored_test_result(test: &Test, config: &Config, message: &str) -> TestResult {
+  outputln!("\x1b[91m{message}\x1b[0m");
+  return TestResult {
+    name: test.name.clone(),
+    status: TestStatus::Failed(TestFailure { error: Some(message.to_string()), ..TestFailure::default() }),
+    duration: Duration::ZERO,
+    method: test.method.clone(),
+    url: format!("{}{}", config.api_hostname, test.endpoint),
+    response_status_code: None,
+    expected_outcome: test.expected_outcome.clone(),
+    attempts: 1,
+    latency: None,
+  };
+}
+
+//None if the test doesnt compare against a snapshot or hasnt recorded one yet
+//the redacted paths are applied to the recorded snapshot as well, so paths added later dont need the snapshot to be rewritten
+fn read_snapshot(test: &Test, path: &Path) -> Result<Option<jzon::JsonValue>, String> {
+  if test.expected_outcome.body_matches_snapshot.is_none() {
+    return Ok(None);
+  }
+  let redact = &test.expected_outcome.body_matches_snapshot.as_ref().unwrap().redact;
+  return snapshot::read(path).map(|x| x.map(|x| snapshot::redact(x, redact)));
+}
+
+//panic! with a message creates a &str or a String payload, anything else cant be shown
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+  if let Some(message) = panic.downcast_ref::<&str>() {
+    return (*message).to_string();
+  }
+  if let Some(message) = panic.downcast_ref::<String>() {
+    return message.clone();
+  }
+  return String::from("unknown cause");
+}
+
+//Runs a single test of a test chain, test_responses and variables are shared with the tests that run after it
+fn run_test(test: &Test, test_chain_name: &str, config: &Config, config_file: &ConfigFile, options: &RunOptions, test_responses: &mut HashMap<String, jzon::JsonValue>, variables: &mut Variables) -> TestResult {
+  let mut test_result = skipped_test_result(test, config, String::from("skip is set"));
+
+  if test.skip {
+    outputln!("skipping test \x1b[96m{}\x1b[0m", test.name);
+    return test_result;
+  }
+  output!("running test \x1b[96m{}\x1b[0m: ", test.name);
+  let test_started = Instant::now();
+  let before_task_results = match test::run_test_before_tasks(test, config, config_file, variables) {
+    Ok(x) => x,
+    Err(e) => return TestResult { duration: test_started.elapsed(), ..errored_test_result(test, config, &e) },
+  };
+  let test = &variables::resolve_test(test, variables);
+  test_result.expected_outcome.clone_from(&test.expected_outcome);
+
+  let snapshot_path = snapshot::path(&options.snapshot_dir, test_chain_name, &test.name);
+  let snapshot = match read_snapshot(test, &snapshot_path) {
+    Ok(x) => x,
+    Err(e) => return TestResult { duration: test_started.elapsed(), ..errored_test_result(test, config, &e) },
+  };
+  //with --update-snapshots a stale snapshot is rewritten anyway, so retrying until it matches would only slow the run down
+  let until_snapshot = snapshot.as_ref().filter(|_| !options.update_snapshots);
+
+  //the before tasks only run once, only the request of the test is sent again
+  let retry = test.retry.clone().unwrap_or(Retry { attempts: 1, delay_ms: 0, until: None });
+  let until_test = Test { expected_outcome: retry.until.clone().unwrap_or(test.expected_outcome.clone()), ..test.clone() };
+  let mut attempt = 1;
+  let response = loop {
+    let response = send_test_request(test, config, &before_task_results, test_responses);
+    if attempt >= retry.attempts || response.as_ref().is_ok_and(|x| test::check_test_result(&until_test, x.status_code, &x.content_type, &x.headers, &x.body, x.duration, until_snapshot, test_responses) == test::TestResults::Passed) {
+      break response;
+    }
+
+    let reason = match &response {
+      Err(e) => e.as_str(),
+      Ok(_) if retry.until.is_some() => "response didnt match until",
+      Ok(_) => "response didnt match expected outcome",
+    };
+    let delay = if retry.delay_ms > 0 { format!(" in {}ms", retry.delay_ms) } else { String::new() };
+    outputln!("\x1b[93mattempt {attempt} of {} failed: {reason}, retrying{delay}\x1b[0m", retry.attempts);
+    thread::sleep(Duration::from_millis(retry.delay_ms));
+    attempt += 1;
+    output!("running test \x1b[96m{}\x1b[0m again: ", test.name);
+  };
+  test_result.attempts = attempt;
+
+  let response = match response {
+    Ok(x) => x,
+    Err(e) => return TestResult { duration: test_started.elapsed(), attempts: attempt, ..errored_test_result(test, config, &e) },
+  };
+  if !response.url.is_empty() {
+    test_result.url.clone_from(&response.url);
+  }
+
+  let mut result = test::check_test_result(test, response.status_code, &response.content_type, &response.headers, &response.body, response.duration, snapshot.as_ref(), test_responses);
+  let mut snapshot_message: Option<String> = None;
+  let snapshot_is_stale = options.update_snapshots && matches!(&result, test::TestResults::Failed(x) if !x.snapshot_diff.is_empty());
+  if test.expected_outcome.body_matches_snapshot.is_some() && (snapshot.is_none() || snapshot_is_stale) {
+    let recorded = snapshot::redact(snapshot::from_body(&response.body), &test.expected_outcome.body_matches_snapshot.as_ref().unwrap().redact);
+    if let Err(e) = snapshot::write(&snapshot_path, &recorded) {
+      return TestResult { duration: test_started.elapsed(), attempts: attempt, ..errored_test_result(test, config, &e) };
+    }
+    snapshot_message = Some(format!("{} snapshot {}", if snapshot.is_none() { "recorded" } else { "updated" }, snapshot_path.display()));
+    result = test::without_snapshot_mismatch(result);
+  }
+  let latency = response.duration.as_millis();
+  match (&result, attempt) {
+    (test::TestResults::Passed, 1) => outputln!("\x1b[92mpassed\x1b[0m ({latency}ms)"),
+    (test::TestResults::Passed, _) => outputln!("\x1b[92mpassed\x1b[0m ({latency}ms) on attempt {attempt} of {}, \x1b[93mflaky\x1b[0m", retry.attempts),
+    (test::TestResults::Failed(_), _) => outputln!("\x1b[91mfailed\x1b[0m ({latency}ms)"),
+  }
+  if let Some(snapshot_message) = snapshot_message {
+    outputln!("\x1b[93m{snapshot_message}\x1b[0m");
+  }
+
+  if test.capture.is_some() {
+    let capture_response = CaptureResponse {
+      status_code: response.status_code,
+      headers: &response.headers,
+      cookies: &response.cookies,
+      body: &jzon::parse(&response.body).unwrap_or(jzon::Null),
+    };
+    for message in variables::capture(test.capture.as_ref().unwrap(), &capture_response, variables) {
+      outputln!("\x1b[93m{message}\x1b[0m");
+    }
+  }
+
+  test_result.duration = test_started.elapsed();
+  test_result.response_status_code = Some(response.status_code);
+  test_result.latency = Some(response.duration);
+  test_result.status = match result {
+    test::TestResults::Passed => TestStatus::Passed,
+    test::TestResults::Failed(failure) => TestStatus::Failed(failure),
+  };
+
+  return test_result;
+}
+
+//Sends the request of a test and reads the whole response, json bodies are added to test_responses for the tests after it
+//Returns an error if sending the request or reading its body timed out or failed
+fn send_test_request(test: &Test, config: &Config, before_task_results: &HashMap<String, String>, test_responses: &mut HashMap<String, jzon::JsonValue>) -> Result<TestResponse, String> {
+  let request_started = Instant::now();
+  let response = test::run_test_http_request(test.clone(), config, before_task_results, test_responses)?;
+  let status_code = response.status();
+  let content_type = String::from(response.content_type());
+  let headers = http_request::get_headers(&response);
+  let cookies = http_request::get_cookies(&response);
+  let url = response.get_url().to_string();
+  let body = http_request::read_body(response, http_request::request_timeout_ms(config, test.timeout_ms))?;
+  let duration = request_started.elapsed();
+
+  if content_type == "application/json" {
+    test_responses.insert(test.name.clone(), jzon::parse(body.as_str()).unwrap_or(jzon::Null));
+  } else {
+    //dont keep the body of an earlier attempt around
+    test_responses.remove(&test.name);
+  }
+
+  return Ok(TestResponse { status_code, content_type, headers, cookies, url, body, duration });
+}
+
+//A started background_cmd is handed to the cleanup right away, so it gets stopped even if the setup fails afterwards
+fn run_setup(config: &Config, worker: Worker, cleanup: &mut Cleanup) -> Result<(), String> {
+  output!("setting up... ");
+  let setup_started = Instant::now();
 
   let output = process::Command::new("sh")
     .arg("-c")
     .arg(&config.setup.cmd)
     .output()
-    .unwrap();
-
+    .map_err(|e| format!("failed to run setup cmd: {e}"))?;
 
+  if !output.status.success() {
+    return Err(format!("setup cmd failed with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+  }
   if !output.stderr.is_empty() {
-    println!("\x1b[91m{}\x1b[0m", String::from_utf8(output.stderr).unwrap_or(String::from("failed to convert stderr of setup")));
-  }
-
-  if !config.setup.finished_condition.endpoint_reachable.is_empty() {
-    let mut request_url = String::from(&config.api_hostname);
-    request_url.push_str(&config.setup.finished_condition.endpoint_reachable);
-    
-    loop {
-      match ureq::get(&request_url).call() {
-        Ok(_) => break,
-        Err(e) => {
-          if e.kind() == ureq::ErrorKind::ConnectionFailed || e.kind() == ureq::ErrorKind::Io {
-            continue;
-          }
-          
-          break;
-        }
-      };
+    outputln!("\x1b[91m{}\x1b[0m", String::from_utf8(output.stderr.clone()).unwrap_or(String::from("failed to convert stderr of setup")));
+  }
+
+  if !config.setup.background_cmd.is_empty() {
+    let log_file = if config.setup.log_file.is_empty() { background_process::default_log_file(&config.name, worker.id) } else { PathBuf::from(&config.setup.log_file) };
+    cleanup.set_background_process(BackgroundProcess::spawn(&config.setup.background_cmd, &log_file).map_err(|e| format!("failed to start background_cmd: {e}"))?);
+    output!("background_cmd logs to {}... ", log_file.display());
+  }
+
+  let finished_condition = &config.setup.finished_condition;
+  if !finished_condition.conditions.is_empty() {
+    let setup_output = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let log_file = cleanup.background_process().map(|x| x.log_file.clone());
+    let context = SetupContext {
+      api_hostname: &config.api_hostname,
+      started: setup_started,
+      output: &setup_output,
+      log_file: log_file.as_deref(),
+    };
+
+    //a background_cmd that exits before the setup finished will never become ready, so there is no point in waiting for the timeout
+    let mut background_exit_status = None;
+    wait_until(finished_condition, |check_timeout| {
+      background_exit_status = cleanup.background_process().and_then(BackgroundProcess::exit_status);
+      if background_exit_status.is_some() {
+        return Ok(());
+      }
+      return readiness::check_all(&finished_condition.conditions, &context, check_timeout);
+    }).map_err(|(timeout_ms, unmet_condition)| format!("setup didnt finish within {timeout_ms}ms, still waiting for {unmet_condition}"))?;
+
+    if let Some(exit_status) = background_exit_status {
+      return Err(format!("background_cmd exited with {exit_status} before the setup finished, see {}", log_file.unwrap_or_default().display()));
     }
   }
 
-  println!("setup completed");
+  outputln!("setup completed");
+  return Ok(());
 }
 
-fn run_cleanup(cmd: &str) {
-  print!("cleaning up... ");
-  process::Command::new("sh")
-    .arg("-c")
-    .arg(cmd)
-    .output()
-    .unwrap();
-  println!("cleanup completed");
-}
\ No newline at end of file
+//Calls check until it returns Ok, sleeping interval_ms between calls and doubling the interval after each call if backoff is set
+//check gets the time left until the timeout, so it can limit how long it blocks itself
+//The last call happens right at the timeout, so a slow start doesnt fail just because the interval is long
+//Returns the timeout in ms and the last error of check, if check didnt return Ok in time
+fn wait_until(condition: &SetupFinishedCondition, mut check: impl FnMut(Duration) -> Result<(), String>) -> Result<(), (u64, String)> {
+  let started = Instant::now();
+  let timeout = Duration::from_millis(condition.timeout_ms);
+  let mut interval = Duration::from_millis(condition.interval_ms);
+
+  loop {
+    let remaining = timeout.saturating_sub(started.elapsed()).clamp(MIN_CHECK_DURATION, MAX_CHECK_DURATION);
+    let Err(e) = check(remaining) else {
+      return Ok(());
+    };
+
+    if started.elapsed() >= timeout {
+      return Err((condition.timeout_ms, e));
+    }
+    thread::sleep(interval.min(timeout.saturating_sub(started.elapsed())));
+
+    if condition.backoff {
+      interval = (interval * 2).min(Duration::from_millis(condition.max_interval_ms.max(condition.interval_ms)));
+    }
+  }
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+  use std::cell::Cell;
+
+  fn condition(timeout_ms: u64, backoff: bool) -> SetupFinishedCondition {
+    return SetupFinishedCondition {
+      conditions: Vec::new(),
+      timeout_ms,
+      interval_ms: 10,
+      backoff,
+      max_interval_ms: 40,
+    };
+  }
+
+  #[test]
+  fn wait_until_returns_once_check_succeeds() {
+    let calls = Cell::new(0);
+    let started = Instant::now();
+
+    assert_eq!(wait_until(&condition(1000, true), |_| { calls.set(calls.get() + 1); if calls.get() == 5 { Ok(()) } else { Err(String::from("not yet")) } }), Ok(()));
+    assert_eq!(calls.get(), 5);
+    //10 + 20 + 40 + 40 with backoff
+    assert!(started.elapsed() >= Duration::from_millis(110));
+  }
+
+  #[test]
+  fn failure_count_stops_at_max_failures() {
+    let failure_count = FailureCount { max_failures: Some(2), failures: AtomicUsize::new(0) };
+    failure_count.add();
+    assert_eq!(failure_count.stop_reason(), None);
+    failure_count.add();
+    assert_eq!(failure_count.stop_reason(), Some(String::from("stopped after 2 failed tests")));
+
+    let unlimited = FailureCount { max_failures: None, failures: AtomicUsize::new(5) };
+    assert_eq!(unlimited.stop_reason(), None);
+  }
+
+  #[test]
+  fn panic_message_of_payloads() {
+    assert_eq!(panic_message(&*panic::catch_unwind(|| panic!("index out of bounds")).unwrap_err()), "index out of bounds");
+    assert_eq!(panic_message(&*panic::catch_unwind(|| panic!("status {}", 500)).unwrap_err()), "status 500");
+    assert_eq!(panic_message(&*panic::catch_unwind(|| std::panic::panic_any(5)).unwrap_err()), "unknown cause");
+  }
+
+  #[test]
+  fn wait_until_times_out() {
+    let calls = Cell::new(0);
+    let started = Instant::now();
+
+    assert_eq!(wait_until(&condition(50, false), |remaining| { calls.set(calls.get() + 1); assert!(remaining <= MAX_CHECK_DURATION); Err(format!("call {}", calls.get())) }), Err((50, format!("call {}", calls.get()))));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(calls.get() >= 5);
+  }
+}
@@ -0,0 +1,116 @@
+use std::env;
+use jzon::JsonValue;
+
+use crate::json_path::{key_path, index_path};
+
+//Replaces ${NAME} and ${NAME:-default} in every string value of the config file with environment variables
+//$${ escapes a literal ${, strings that cant be interpolated are left as they are and returned with their path and why
+pub fn interpolate(value: &JsonValue) -> (JsonValue, Vec<(String, String)>) {
+  let mut errors: Vec<(String, String)> = Vec::new();
+  let output = interpolate_json(value, "$", &|name| env::var(name).ok(), &mut errors);
+  return (output, errors);
+}
+
+pub fn interpolate_json(value: &JsonValue, path: &str, get_var: &dyn Fn(&str) -> Option<String>, errors: &mut Vec<(String, String)>) -> JsonValue {
+  if value.is_string() {
+    let text = value.as_str().unwrap_or_default();
+    return match interpolate_str(text, get_var) {
+      Ok(x) => x.into(),
+      Err(e) => {
+        errors.push((path.to_string(), e));
+        text.into()
+      },
+    };
+  }
+  if value.is_array() {
+    return value.members().enumerate().map(|(i, x)| interpolate_json(x, &index_path(path, i), get_var, errors)).collect::<Vec<JsonValue>>().into();
+  }
+  if value.is_object() {
+    let mut output = JsonValue::new_object();
+    for (key, entry) in value.entries() {
+      output[key] = interpolate_json(entry, &key_path(path, key), get_var, errors);
+    }
+    return output;
+  }
+  return value.clone();
+}
+
+//Like the shell, the default is also used when the variable is set but empty
+pub fn interpolate_str(value: &str, get_var: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+  let mut output = String::new();
+  let mut rest = value;
+
+  while let Some(start) = rest.find('$') {
+    output.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    if let Some(after_escape) = rest.strip_prefix("$${") {
+      output.push_str("${");
+      rest = after_escape;
+      continue;
+    }
+    let Some(after_brace) = rest.strip_prefix("${") else {
+      output.push('$');
+      rest = &rest[1..];
+      continue;
+    };
+    let Some(end) = after_brace.find('}') else {
+      return Err(format!("unterminated ${{ in \"{value}\""));
+    };
+
+    let expression = &after_brace[..end];
+    let (name, default) = match expression.split_once(":-") {
+      Some((name, default)) => (name, Some(default)),
+      None => (expression, None),
+    };
+    if name.is_empty() || !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+      return Err(format!("\"{name}\" is not a valid environment variable name"));
+    }
+
+    match (get_var(name).filter(|x| !x.is_empty() || default.is_none()), default) {
+      (Some(x), _) => output.push_str(&x),
+      (None, Some(default)) => output.push_str(default),
+      (None, None) => return Err(format!("environment variable {name} is not set and has no default")),
+    }
+    rest = &after_brace[end + 1..];
+  }
+
+  output.push_str(rest);
+  return Ok(output);
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  fn get_var(name: &str) -> Option<String> {
+    return match name {
+      "API_HOST" => Some("http://api:4000".to_string()),
+      "EMPTY" => Some(String::new()),
+      _ => None,
+    };
+  }
+
+  #[test]
+  fn interpolate_str_replaces_variables_and_defaults() {
+    assert_eq!(interpolate_str("${API_HOST}/api", &get_var), Ok("http://api:4000/api".to_string()));
+    assert_eq!(interpolate_str("${ADMIN_SECRET:-changeme}", &get_var), Ok("changeme".to_string()));
+    assert_eq!(interpolate_str("${EMPTY:-fallback}|${EMPTY}", &get_var), Ok("fallback|".to_string()));
+    assert_eq!(interpolate_str("costs 5$ and $login.token $${API_HOST}", &get_var), Ok("costs 5$ and $login.token ${API_HOST}".to_string()));
+  }
+
+  #[test]
+  fn interpolate_str_reports_errors() {
+    assert_eq!(interpolate_str("${ADMIN_SECRET}", &get_var), Err("environment variable ADMIN_SECRET is not set and has no default".to_string()));
+    assert_eq!(interpolate_str("${API_HOST", &get_var), Err("unterminated ${ in \"${API_HOST\"".to_string()));
+    assert_eq!(interpolate_str("${API HOST}", &get_var), Err("\"API HOST\" is not a valid environment variable name".to_string()));
+  }
+
+  #[test]
+  fn interpolate_json_only_changes_strings() {
+    let value = jzon::parse(r#"{"api_hostname":"${API_HOST}","body":{"secret":"${SECRET:-changeme}","id":5},"${API_HOST}":["${EMPTY}","${MISSING}"]}"#).unwrap();
+    let mut errors: Vec<(String, String)> = Vec::new();
+    assert_eq!(interpolate_json(&value, "$", &get_var, &mut errors).dump(), r#"{"api_hostname":"http://api:4000","body":{"secret":"changeme","id":5},"${API_HOST}":["","${MISSING}"]}"#);
+    assert_eq!(errors, vec![(String::from("$[\"${API_HOST}\"][1]"), String::from("environment variable MISSING is not set and has no default"))]);
+  }
+}
@@ -1,77 +1,281 @@
 use std::collections::HashMap;
+use std::{error, io};
+use std::time::Duration;
 use linked_hash_map::LinkedHashMap;
 
 use crate::Config;
+use crate::output::outputln;
 
-pub fn send(config: &Config, method: &str, endpoint: &str, body: Option<&str>, cookies: Option<&LinkedHashMap<String, String>>, before_task_results: Option<&HashMap<String, String>>) -> ureq::Response {
+//Returns an error if the server didnt answer within the timeout, other errors without a response are reported as status 999
+#[allow(clippy::too_many_arguments)]
+pub fn send(config: &Config, method: &str, endpoint: &str, body: Option<&str>, cookies: Option<&LinkedHashMap<String, String>>, headers: Option<&LinkedHashMap<String, String>>, before_task_results: Option<&HashMap<String, String>>, timeout_ms: Option<u64>) -> Result<ureq::Response, String> {
   let mut request_url = String::from(&config.api_hostname);
   request_url.push_str(endpoint); 
   
   let cookie_string = if cookies.is_some() {
-    parse_cookies(cookies.unwrap(), before_task_results.unwrap())
+    parse_cookies(cookies.unwrap(), before_task_results)
   } else {
     String::new()
   };
 
-  let cookie_string = cookie_string.as_str();
-  
-  let res = match method {
-    "GET" => {
-      ureq::get(request_url.as_str())
-        .set("Cookie", cookie_string)
-        .call()
-    },
-    "DELETE" => {
-      ureq::delete(request_url.as_str())
-        .set("Cookie", cookie_string)
-        .call()
-    },
-    "POST" => {
-      ureq::post(request_url.as_str())
-        .set("Content-Type", "application/json")
-        .set("Cookie", cookie_string)
-        .send_string(body.unwrap_or(""))
-    },
-    "PUT" => {
-      ureq::put(request_url.as_str())
-        .set("Content-Type", "application/json")
-        .set("Cookie", cookie_string)
-        .send_string(body.unwrap_or(""))
-    },
-    _ => panic!("tried to send http request with unrecognized method {method}"),
+  let mut request = ureq::request(method, request_url.as_str());
+  let timeout_ms = request_timeout_ms(config, timeout_ms);
+  if timeout_ms.is_some() {
+    request = request.timeout(Duration::from_millis(timeout_ms.unwrap()));
+  }
+
+  //methods that usually carry a body keep getting one, even if its empty, as thats what trest always did for POST and PUT
+  let has_body = body.is_some() || matches!(method, "POST" | "PUT" | "PATCH");
+  if has_body {
+    request = request.set("Content-Type", "application/json");
+  }
+
+  request = request.set("Cookie", cookie_string.as_str());
+
+  if headers.is_some() {
+    for (name, value) in headers.unwrap() {
+      request = request.set(name, resolve_task_references(value, before_task_results).as_str());
+    }
+  }
+
+  let res = if has_body {
+    request.send_string(body.unwrap_or(""))
+  } else {
+    request.call()
   };
 
   match res {
-    Ok(x) => return x,
+    Ok(x) => return Ok(x),
+    Err(e) if timeout_ms.is_some() && is_timeout(&e) => return Err(timed_out_message(timeout_ms.unwrap())),
     Err(e) => {
       let error_string = format!("{e:?}");
-      return e.into_response().unwrap_or_else(|| {
-        println!("couldn't parse response\n{error_string}");
+      return Ok(e.into_response().unwrap_or_else(|| {
+        outputln!("couldn't parse response\n{error_string}");
         ureq::Response::new(999, "", "").unwrap()
-      });
+      }));
     }
   };
 }
 
-fn parse_cookies(cookies: &LinkedHashMap<String, String>, before_task_results: &HashMap<String, String>) -> String {
+//The timeout of a test or task if it sets one, otherwise the one of the config or --request-timeout
+pub fn request_timeout_ms(config: &Config, timeout_ms: Option<u64>) -> Option<u64> {
+  return timeout_ms.or(config.request_timeout_ms);
+}
+
+//The timeout also covers reading the body, which only happens after send returned
+pub fn read_body(response: ureq::Response, timeout_ms: Option<u64>) -> Result<String, String> {
+  return response.into_string().map_err(|e| {
+    if timeout_ms.is_some() && e.kind() == io::ErrorKind::TimedOut {
+      return timed_out_message(timeout_ms.unwrap());
+    }
+    return format!("couldnt read response body: {e}");
+  });
+}
+
+fn timed_out_message(timeout_ms: u64) -> String {
+  return format!("request timed out after {timeout_ms}ms");
+}
+
+//ureq wraps the io error of a timeout, so the whole chain of sources is checked
+fn is_timeout(error: &ureq::Error) -> bool {
+  let mut source: Option<&(dyn error::Error + 'static)> = Some(error);
+  while let Some(x) = source {
+    if x.downcast_ref::<io::Error>().is_some_and(|x| matches!(x.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)) {
+      return true;
+    }
+    source = x.source();
+  }
+  return false;
+}
+
+//Collects all response headers with lowercased names, so lookups dont depend on the casing the server used
+//Headers that appear multiple times, like set-cookie, are joined with a comma
+pub fn get_headers(response: &ureq::Response) -> LinkedHashMap<String, String> {
+  return response.headers_names()
+    .iter()
+    .map(|name| (name.to_lowercase(), response.all(name).join(", ")))
+    .collect();
+}
+
+//Collects the name and value of every cookie the response sets, attributes like Path or Expires are left out
+pub fn get_cookies(response: &ureq::Response) -> LinkedHashMap<String, String> {
+  return response.all("set-cookie")
+    .iter()
+    .filter_map(|x| x.split(';').next().unwrap_or_default().split_once('='))
+    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+    .collect();
+}
+
+fn parse_cookies(cookies: &LinkedHashMap<String, String>, before_task_results: Option<&HashMap<String, String>>) -> String {
   return cookies
     .iter()
-    .map(|(key, value)| {
-      let mut output = String::new();
-      output.push_str(key);
-      output.push('=');
-
-      if value.starts_with('$') {
-        let before_task_results = before_task_results.clone();
-        let values = before_task_results.get(value.replace('$', "").split('.').collect::<Vec<&str>>()[0]).unwrap();
-        let values = jzon::parse(values).unwrap();
-        output.push_str(values[value.replace('$', "").split('.').collect::<Vec<&str>>()[1]].as_str().unwrap_or(""));
-      } else {
-        output.push_str(value);
-      }
-      
-      return output;
-    })
+    .map(|(key, value)| format!("{key}={}", resolve_task_references(value, before_task_results)))
     .collect::<Vec<String>>()
     .join("; ");
-}
\ No newline at end of file
+}
+
+//Replaces all $task_name.key references in value with the key from the json response of that before task
+//References to tasks that didnt run are left as they are
+fn resolve_task_references(value: &str, before_task_results: Option<&HashMap<String, String>>) -> String {
+  if before_task_results.is_none() {
+    return value.to_string();
+  }
+
+  let task_names: Vec<&str> = before_task_results.unwrap().keys().map(String::as_str).collect();
+  let mut output = value.to_string();
+  for (reference, task_name, key) in find_task_references(value, &task_names).into_iter().rev() {
+    let task_result = jzon::parse(&before_task_results.unwrap()[&task_name]).unwrap_or(jzon::Null);
+    output.replace_range(reference, task_result[key.as_str()].as_str().unwrap_or(""));
+  }
+  return output;
+}
+
+//Finds all references looking like $task_name.key to one of the given tasks and returns their position, the task name and the key
+//Task names can contain any character, so a $ that isnt followed by the name of a task and a dot is just part of the value, like in $5.00
+pub fn find_task_references(value: &str, task_names: &[&str]) -> Vec<(std::ops::Range<usize>, String, String)> {
+  let is_key_char = |x: char| x.is_ascii_alphanumeric() || x == '_' || x == '-';
+  let mut output = Vec::new();
+
+  for (start, _) in value.match_indices('$') {
+    let rest = &value[start + 1..];
+    //the longest name wins if one task name is the start of another
+    let Some(task_name) = task_names.iter().filter(|x| !x.is_empty() && rest.starts_with(**x) && rest[x.len()..].starts_with('.')).max_by_key(|x| x.len()) else {
+      continue;
+    };
+    let key_start = start + 1 + task_name.len() + 1;
+    let key: String = value[key_start..].chars().take_while(|x| is_key_char(*x)).collect();
+    if key.is_empty() {
+      continue;
+    }
+    output.push((start..key_start + key.len(), (*task_name).to_string(), key));
+  }
+
+  return output;
+}
+
+#[cfg(test)]
+pub(crate) mod unit_test {
+  use super::*;
+
+  use std::io::{BufRead, BufReader, Read, Write};
+  use std::net::TcpListener;
+  use std::thread;
+
+  pub(crate) fn config_for(listener: &TcpListener) -> Config {
+    return Config {
+      name: "backend".to_string(),
+      description: String::new(),
+      setup: crate::SetupConfig {
+        cmd: String::new(),
+        background_cmd: String::new(),
+        log_file: String::new(),
+        finished_condition: crate::SetupFinishedCondition { conditions: Vec::new(), timeout_ms: 300_000, interval_ms: 100, backoff: false, max_interval_ms: 5000 },
+      },
+      cleanup: crate::CleanupConfig { cmd: String::new() },
+      api_hostname: format!("http://{}", listener.local_addr().unwrap()),
+      request_timeout_ms: Some(5000),
+    };
+  }
+
+  //Sends a request to a local listener and returns the request line, the lowercased headers and the body it received
+  fn capture_request(method: &str, body: Option<&str>) -> (String, LinkedHashMap<String, String>, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = config_for(&listener);
+    let server = thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let mut request_line = String::new();
+      reader.read_line(&mut request_line).unwrap();
+
+      let mut headers: LinkedHashMap<String, String> = LinkedHashMap::new();
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let Some((name, value)) = line.trim_end().split_once(':') else { break };
+        headers.insert(name.to_lowercase(), value.trim().to_string());
+      }
+
+      let content_length: usize = headers.get("content-length").map_or(0, |x| x.parse().unwrap());
+      let mut body = vec![0; content_length];
+      reader.read_exact(&mut body).unwrap();
+      stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+      return (request_line.trim_end().to_string(), headers, String::from_utf8(body).unwrap());
+    });
+
+    let res = send(&config, method, "/items", body, None, None, None, None).unwrap();
+    assert_eq!(res.status(), 204);
+    return server.join().unwrap();
+  }
+
+  #[test]
+  fn send_reports_timeout() {
+    //the listener accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = config_for(&listener);
+
+    let res = send(&config, "GET", "/", None, None, None, None, Some(50));
+    assert_eq!(res.err(), Some(String::from("request timed out after 50ms")));
+  }
+
+  #[test]
+  fn send_uses_method_and_body() {
+    let (request_line, headers, body) = capture_request("PATCH", Some(r#"{"name":"a"}"#));
+    assert_eq!(request_line, "PATCH /items HTTP/1.1");
+    assert_eq!(headers.get("content-type").map(String::as_str), Some("application/json"));
+    assert_eq!(body, r#"{"name":"a"}"#);
+
+    //PATCH gets a body even without one, like POST and PUT
+    let (request_line, headers, body) = capture_request("PATCH", None);
+    assert_eq!(request_line, "PATCH /items HTTP/1.1");
+    assert_eq!(headers.get("content-type").map(String::as_str), Some("application/json"));
+    assert_eq!(body, "");
+
+    let (request_line, headers, body) = capture_request("PURGE", Some("{}"));
+    assert_eq!(request_line, "PURGE /items HTTP/1.1");
+    assert_eq!(headers.get("content-type").map(String::as_str), Some("application/json"));
+    assert_eq!(body, "{}");
+
+    for method in ["HEAD", "OPTIONS"] {
+      let (request_line, headers, body) = capture_request(method, None);
+      assert_eq!(request_line, format!("{method} /items HTTP/1.1"));
+      assert_eq!(headers.get("content-type"), None);
+      assert_eq!(headers.get("content-length"), None);
+      assert_eq!(body, "");
+    }
+  }
+
+  #[test]
+  fn resolve_task_references_replaces_whole_value() {
+    let before_task_results: HashMap<String, String> = vec![("login".to_string(), r#"{"accessToken":"abc"}"#.to_string())].into_iter().collect();
+
+    assert_eq!(resolve_task_references("$login.accessToken", Some(&before_task_results)), "abc");
+  }
+
+  #[test]
+  fn resolve_task_references_replaces_inside_value() {
+    let before_task_results: HashMap<String, String> = vec![("login".to_string(), r#"{"accessToken":"abc"}"#.to_string())].into_iter().collect();
+
+    assert_eq!(resolve_task_references("Bearer $login.accessToken", Some(&before_task_results)), "Bearer abc");
+  }
+
+  #[test]
+  fn resolve_task_references_leaves_unknown_and_plain_values() {
+    let before_task_results: HashMap<String, String> = HashMap::new();
+
+    assert_eq!(resolve_task_references("costs 5$ or $other.key", Some(&before_task_results)), "costs 5$ or $other.key");
+    assert_eq!(resolve_task_references("$login.accessToken", None), "$login.accessToken");
+  }
+
+  #[test]
+  fn resolve_task_references_with_any_task_name() {
+    let before_task_results: HashMap<String, String> = vec![
+      ("login as admin".to_string(), r#"{"accessToken":"abc"}"#.to_string()),
+      ("login".to_string(), r#"{"accessToken":"def"}"#.to_string()),
+    ].into_iter().collect();
+
+    assert_eq!(resolve_task_references("$login as admin.accessToken", Some(&before_task_results)), "abc");
+    assert_eq!(resolve_task_references("$login.accessToken and $login as admin.accessToken", Some(&before_task_results)), "def and abc");
+    assert_eq!(resolve_task_references("$5.00", Some(&before_task_results)), "$5.00");
+    assert_eq!(find_task_references("$5.00 or $login.", &["login", "login as admin"]), Vec::new());
+  }
+}
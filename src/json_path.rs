@@ -0,0 +1,173 @@
+use jzon::JsonValue;
+
+//Paths look like $.configs[0].name, keys that arent plain identifiers are quoted like $["login as admin"]
+//Negative indexes count from the end of an array and .length returns the length of an array, object or string
+//[*] stands for every element of an array or value of an object, it can only be set and not read
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+  Key(String),
+  Index(i64),
+  Wildcard,
+}
+
+pub fn key_path(parent: &str, key: &str) -> String {
+  if !key.is_empty() && key.chars().all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-') {
+    return format!("{parent}.{key}");
+  }
+  return format!("{parent}[{}]", JsonValue::from(key).dump());
+}
+
+pub fn index_path(parent: &str, index: usize) -> String {
+  return format!("{parent}[{index}]");
+}
+
+pub fn parse(path: &str) -> Result<Vec<Segment>, String> {
+  let Some(mut rest) = path.strip_prefix('$') else {
+    return Err(format!("path \"{path}\" has to start with $"));
+  };
+  let mut output: Vec<Segment> = Vec::new();
+
+  while !rest.is_empty() {
+    if let Some(after_dot) = rest.strip_prefix('.') {
+      let key_length = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+      if key_length == 0 {
+        return Err(format!("path \"{path}\" contains an empty key"));
+      }
+      output.push(Segment::Key(after_dot[..key_length].to_string()));
+      rest = &after_dot[key_length..];
+    } else if let Some(after_bracket) = rest.strip_prefix('[') {
+      let (segment, length) = if after_bracket.starts_with('"') {
+        let end = find_closing_quote(after_bracket).ok_or(format!("path \"{path}\" contains an unterminated key"))?;
+        let key = jzon::parse(&after_bracket[..=end]).map_err(|_| format!("path \"{path}\" contains an invalid key"))?;
+        (Segment::Key(key.as_str().unwrap_or_default().to_string()), end + 1)
+      } else if after_bracket.starts_with('*') {
+        (Segment::Wildcard, 1)
+      } else {
+        let end = after_bracket.find(']').ok_or(format!("path \"{path}\" contains an unterminated index"))?;
+        let index = after_bracket[..end].trim().parse::<i64>().map_err(|_| format!("path \"{path}\" contains the invalid index \"{}\"", &after_bracket[..end]))?;
+        (Segment::Index(index), end)
+      };
+      let Some(after_segment) = after_bracket[length..].strip_prefix(']') else {
+        return Err(format!("path \"{path}\" is missing a closing ]"));
+      };
+      output.push(segment);
+      rest = after_segment;
+    } else {
+      return Err(format!("path \"{path}\" has an unexpected character at \"{rest}\""));
+    }
+  }
+
+  return Ok(output);
+}
+
+//Returns the value at path, or None if there is nothing at that path
+pub fn get(value: &JsonValue, path: &[Segment]) -> Option<JsonValue> {
+  let Some((segment, rest)) = path.split_first() else {
+    return Some(value.clone());
+  };
+
+  let next = match segment {
+    Segment::Key(key) if value.is_object() && value.has_key(key) => value[key.as_str()].clone(),
+    Segment::Key(key) if key == "length" && (value.is_array() || value.is_object()) => value.len().into(),
+    Segment::Key(key) if key == "length" && value.is_string() => value.as_str().unwrap_or_default().chars().count().into(),
+    Segment::Index(index) if value.is_array() => {
+      let index = if *index < 0 { i64::try_from(value.len()).unwrap_or(i64::MAX) + index } else { *index };
+      value.members().nth(usize::try_from(index).ok()?)?.clone()
+    },
+    _ => return None,
+  };
+
+  return get(&next, rest);
+}
+
+//Replaces the values at path with replacement, paths that dont exist are left as they are instead of being created
+pub fn set(value: &mut JsonValue, path: &[Segment], replacement: &JsonValue) {
+  let Some((segment, rest)) = path.split_first() else {
+    *value = replacement.clone();
+    return;
+  };
+
+  match segment {
+    Segment::Key(key) if value.is_object() && value.has_key(key) => set(&mut value[key.as_str()], rest, replacement),
+    Segment::Index(index) if value.is_array() => {
+      let index = if *index < 0 { i64::try_from(value.len()).unwrap_or(i64::MAX) + index } else { *index };
+      if let Some(next) = usize::try_from(index).ok().and_then(|x| value.members_mut().nth(x)) {
+        set(next, rest, replacement);
+      }
+    },
+    Segment::Wildcard if value.is_array() => value.members_mut().for_each(|x| set(x, rest, replacement)),
+    Segment::Wildcard if value.is_object() => value.entries_mut().for_each(|(_, x)| set(x, rest, replacement)),
+    _ => (),
+  }
+}
+
+fn find_closing_quote(input: &str) -> Option<usize> {
+  let mut escaped = false;
+  for (i, x) in input.char_indices().skip(1) {
+    match x {
+      '\\' if !escaped => escaped = true,
+      '"' if !escaped => return Some(i),
+      _ => escaped = false,
+    }
+  }
+  return None;
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn key_path_quotes_special_characters() {
+    assert_eq!(key_path("$.tasks", "login_as_admin"), "$.tasks.login_as_admin");
+    assert_eq!(key_path("$.tasks", "login as admin"), "$.tasks[\"login as admin\"]");
+  }
+
+  #[test]
+  fn parse_all_segment_types() {
+    assert_eq!(parse(r#"$.items[0]["first name"][-1].length"#), Ok(vec![
+      Segment::Key("items".to_string()),
+      Segment::Index(0),
+      Segment::Key("first name".to_string()),
+      Segment::Index(-1),
+      Segment::Key("length".to_string()),
+    ]));
+    assert_eq!(parse("$"), Ok(Vec::new()));
+    assert_eq!(parse("$.items[*].id"), Ok(vec![Segment::Key("items".to_string()), Segment::Wildcard, Segment::Key("id".to_string())]));
+  }
+
+  #[test]
+  fn parse_invalid_paths() {
+    assert!(parse("items").is_err());
+    assert!(parse("$.").is_err());
+    assert!(parse("$[a]").is_err());
+    assert!(parse("$[\"a\"").is_err());
+  }
+
+  #[test]
+  fn get_values() {
+    let value = jzon::parse(r#"{"items":[{"id":1},{"id":2,"name":"two"}],"length":"own key","user":{"role":"admin"}}"#).unwrap();
+
+    assert_eq!(get(&value, &parse("$.items.length").unwrap()), Some(2.into()));
+    assert_eq!(get(&value, &parse("$.items[-1].name").unwrap()), Some("two".into()));
+    assert_eq!(get(&value, &parse("$.items[1].name.length").unwrap()), Some(3.into()));
+    assert_eq!(get(&value, &parse("$.user.role").unwrap()), Some("admin".into()));
+    assert_eq!(get(&value, &parse("$.length").unwrap()), Some("own key".into()));
+    assert_eq!(get(&value, &parse("$.items[2]").unwrap()), None);
+    assert_eq!(get(&value, &parse("$.user.name").unwrap()), None);
+    assert_eq!(get(&value, &parse("$.items[*].id").unwrap()), None);
+  }
+
+  #[test]
+  fn set_existing_values() {
+    let mut value = jzon::parse(r#"{"items":[{"id":1,"created":"a"},{"id":2}],"meta":{"a":{"at":1},"b":{"at":2}}}"#).unwrap();
+    let replacement = JsonValue::from("x");
+
+    set(&mut value, &parse("$.items[*].created").unwrap(), &replacement);
+    set(&mut value, &parse("$.meta[*].at").unwrap(), &replacement);
+    set(&mut value, &parse("$.items[-1].id").unwrap(), &replacement);
+    set(&mut value, &parse("$.missing.key").unwrap(), &replacement);
+    assert_eq!(value.dump(), r#"{"items":[{"id":1,"created":"x"},{"id":"x"}],"meta":{"a":{"at":"x"},"b":{"at":"x"}}}"#);
+  }
+}
@@ -7,105 +7,248 @@
   clippy::wildcard_imports,
   clippy::unnecessary_unwrap,
   clippy::module_name_repetitions,
-  clippy::needless_return,
 )]
 
 use linked_hash_map::LinkedHashMap;
 use linked_hash_set::LinkedHashSet;
 use std::{env, fs, process};
+use test::assertion::AssertionOperator;
 
 mod http_request;
 mod test;
 mod task;
 mod config;
+mod validation;
+mod json_path;
+mod report;
+mod variables;
+mod environment;
+mod readiness;
+mod background_process;
+mod cleanup;
+mod output;
+mod cli;
+mod tags;
+mod snapshot;
+mod util;
+
+//exit code used when the config file cant be read, parsed or fails validation or an argument is invalid, to tell it apart from failed tests
+const EXIT_CODE_CONFIG_INVALID: i32 = 2;
+//exit code used when the setup of a config didnt finish, so tests couldnt run at all
+const EXIT_CODE_SETUP_FAILED: i32 = 3;
+
+//how long to wait between checks of the setup finished_condition, if not configured
+const DEFAULT_SETUP_INTERVAL_MS: u64 = 100;
+//a setup that never becomes ready fails after 5 minutes instead of blocking the run forever
+const DEFAULT_SETUP_TIMEOUT_MS: u64 = 300_000;
+const DEFAULT_SETUP_MAX_INTERVAL_MS: u64 = 5000;
+
+//the port of the first worker with --jobs, so a single worker uses the port of the usual api_hostname
+const DEFAULT_WORKER_BASE_PORT: usize = 4000;
+
+//written by trest init
+const INIT_CONFIG_FILE: &str = include_str!("../test/init.json");
 
 fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let command = cli::parse(&args).unwrap_or_else(|e| {
+    println!("\x1b[91m{e}\x1b[0m\nsee trest --help for the available subcommands and options");
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  });
+
+  match command {
+    cli::Command::Help => print!("{}", cli::HELP),
+    cli::Command::Version => println!("trest {}", env!("CARGO_PKG_VERSION")),
+    cli::Command::Init(path) => init(&path),
+    cli::Command::Validate(path) => {
+      get_config_file(&path);
+      println!("Config file {path} is valid");
+    },
+    cli::Command::List(path, filters) => {
+      let mut config_file = get_config_file(&path);
+      filters.apply(&mut config_file);
+      list(&config_file, &filters.tags);
+    },
+    cli::Command::Run(args) => run(args),
+  }
+}
+
+fn run(args: cli::RunArgs) {
   println!("Starting trest");
+  cleanup::handle_termination_signals();
 
-  let args = get_args();
-  let mut config_file = get_config_file();
+  let mut config_file = get_config_file(&args.config_path);
   println!("Config file read");
-  
-  if args.config_to_run.is_some() {
-    config_file.configs = config_file.configs.iter().filter(|x| x.name == args.config_to_run.clone().unwrap()).cloned().collect();
-    println!("Only running config {}", args.config_to_run.unwrap());
+
+  if !args.filters.is_empty() {
+    args.filters.apply(&mut config_file);
+    println!("Only running configs, test chains and tests matching {}", args.filters);
   }
 
-  if args.test_to_run.is_some() {
-    config_file.tests = config_file.tests.iter().filter(|x| x.name == args.test_to_run.clone().unwrap()).cloned().collect();
-    println!("Only running test chain {}", args.test_to_run.unwrap());
+  if args.request_timeout_ms.is_some() {
+    config_file.configs = config_file.configs.into_iter().map(|x| Config { request_timeout_ms: x.request_timeout_ms.or(args.request_timeout_ms), ..x }).collect();
+  }
+
+  if args.rerun_failed.is_some() {
+    let retry = Retry { attempts: args.rerun_failed.unwrap().saturating_add(1), delay_ms: 0, until: None };
+    config_file.tests = config_file.tests.into_iter().map(|test_chain| TestChain {
+      tests: test_chain.tests.into_iter().map(|test| Test { retry: test.retry.or(Some(retry.clone())), ..test }).collect(),
+      ..test_chain
+    }).collect();
   }
 
   println!("There are {} configs to run", config_file.configs.len());
 
-  let mut tests_failed = false;
+  if args.jobs > 1 {
+    println!("Running up to {} test chains at the same time", args.jobs);
+  }
+
+  let (config_results, duration) = config::run_all(&config_file, &config::RunOptions {
+    jobs: args.jobs,
+    worker_base_port: args.worker_base_port,
+    max_failures: args.max_failures,
+    tag_filter: args.filters.tags.clone(),
+    snapshot_dir: snapshot::directory(&args.config_path),
+    update_snapshots: args.update_snapshots,
+  });
+
+  if args.report_junit.is_some() {
+    match report::junit::write(args.report_junit.as_ref().unwrap(), &config_results) {
+      Ok(()) => println!("Wrote JUnit report to {}", args.report_junit.unwrap()),
+      Err(e) => println!("\x1b[91mfailed to write JUnit report to {}: {e}\x1b[0m", args.report_junit.unwrap()),
+    }
+  }
 
-  for config in config_file.configs.iter() {
-    if config::run(config, &config_file) {
-      tests_failed = true;
+  if args.report_json.is_some() {
+    match report::json::write(args.report_json.as_ref().unwrap(), &config_results, duration) {
+      Ok(()) => println!("Wrote JSON report to {}", args.report_json.unwrap()),
+      Err(e) => println!("\x1b[91mfailed to write JSON report to {}: {e}\x1b[0m", args.report_json.unwrap()),
     }
   }
 
-  if tests_failed {
+  if config_results.iter().any(report::ConfigResult::has_setup_errors) {
+    process::exit(EXIT_CODE_SETUP_FAILED);
+  } else if config_results.iter().any(report::ConfigResult::has_failures) {
     process::exit(1);
   } else {
     process::exit(0);
   }
 }
 
-fn get_args() -> CliArgs {
-  let args: Vec<String> = std::env::args().collect();
+//Prints what trest run would run with the same filters, without running anything
+fn list(config_file: &ConfigFile, tag_filter: &tags::TagFilter) {
+  println!("configs:");
+  for config in &config_file.configs {
+    if config.description.is_empty() {
+      println!("  \x1b[96m{}\x1b[0m", config.name);
+    } else {
+      println!("  \x1b[96m{}\x1b[0m: {}", config.name, config.description);
+    }
+  }
 
-  return CliArgs {
-    config_to_run: match Into::<Option<String>>::into(args.iter().cloned().filter(|x| x.starts_with("--config_to_run=")).collect::<Vec<String>>().first().cloned().unwrap_or_default()) {
-      Some(x) => if x.is_empty() {None} else {Some(x.replace("--config_to_run=", ""))}
-      None => None,
-    },
-    test_to_run: match Into::<Option<String>>::into(args.iter().cloned().filter(|x| x.starts_with("--test_to_run=")).collect::<Vec<String>>().first().cloned().unwrap_or_default()) {
-      Some(x) => if x.is_empty() {None} else {Some(x.replace("--test_to_run=", ""))}
-      None => None,
-    },
-  };
+  println!("test chains:");
+  for test_chain in &config_file.tests {
+    println!("  \x1b[96m{}\x1b[0m", test_chain.name);
+    for test in &test_chain.tests {
+      let tags = if test.tags.is_empty() { String::new() } else { format!(" [{}]", test.tags.join(", ")) };
+      let status = if tag_filter.deselect_reason(&test.tags).is_some() { " (deselected)" } else if test.skip { " (skipped)" } else { "" };
+      println!("    {}{tags}{status}", test.name);
+    }
+  }
+}
+
+//Writes an example config file to start from, an existing file is never overwritten
+fn init(path: &str) {
+  if fs::metadata(path).is_ok() {
+    println!("\x1b[91m{path} already exists, remove it or pass another path to trest init\x1b[0m");
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  }
+  if let Err(e) = fs::write(path, INIT_CONFIG_FILE) {
+    println!("\x1b[91mfailed to write {path}: {e}\x1b[0m");
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  }
+  println!("Wrote an example config file to {path}, run it with trest {path}");
 }
 
-fn get_config_file() -> ConfigFile {
-  let args: Vec<String> = env::args().collect();
-  assert!((args.len() >= 2), "failed to get config file path");
+fn get_config_file(path: &str) -> ConfigFile {
+  let config_file = fs::read_to_string(path).unwrap_or_else(|e| {
+    println!("\x1b[91mfailed to read config file {path}: {e}\x1b[0m");
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  });
+  let json_pared_config_file = jzon::parse(&config_file).unwrap_or_else(|e| {
+    println!("\x1b[91mfailed to parse config file {path}: {e}\x1b[0m");
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  });
+
+  let config_directory = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new("."));
+  let (interpolated_config_file, validation_errors) = validation::validate(&json_pared_config_file, &config_file, config_directory);
+  if !validation_errors.is_empty() {
+    println!("\x1b[91mconfig file {path} is invalid, found {} errors:\x1b[0m", validation_errors.len());
+    for validation_error in validation_errors {
+      println!("\x1b[91m  {validation_error}\x1b[0m");
+    }
+    process::exit(EXIT_CODE_CONFIG_INVALID);
+  }
 
-  let config_file = fs::read_to_string(args[1].clone()).expect("failed to read config file");
-  let json_pared_config_file = jzon::parse(&config_file).expect("failed to parse config");
   let timestamp_before = std::time::Instant::now();
-  let parsed_config_file = parse_config_file(&json_pared_config_file);
+  let parsed_config_file = parse_config_file(&interpolated_config_file);
   println!("Parsed config file in {}ms", timestamp_before.elapsed().as_millis());
   return parsed_config_file;
 }
 
+//Merges all arrays of tags into one, duplicates keep the position they first appeared at
+fn parse_tags(inputs: &[&jzon::JsonValue]) -> Vec<String> {
+  let mut output: Vec<String> = Vec::new();
+  for tag in inputs.iter().flat_map(|x| x.members()).map(std::string::ToString::to_string) {
+    if !output.contains(&tag) {
+      output.push(tag);
+    }
+  }
+  return output;
+}
+
 fn parse_config_file(input: &jzon::JsonValue) -> ConfigFile {
   return ConfigFile { 
     configs: input["configs"].members().map(|x| Config {
       name: x["name"].to_string(),
-      description: x["description"].to_string(),
+      description: x["description"].as_str().unwrap_or_default().to_string(),
       setup: SetupConfig {
-        cmd: x["setup"]["cmd"].to_string(),
+        cmd: x["setup"]["cmd"].as_str().unwrap_or_default().to_string(),
+        background_cmd: x["setup"]["background_cmd"].as_str().unwrap_or_default().to_string(),
+        log_file: x["setup"]["log_file"].as_str().unwrap_or_default().to_string(),
         finished_condition: SetupFinishedCondition { 
-          endpoint_reachable: x["setup"]["finished_condition"]["endpoint_reachable"].to_string(),
+          conditions: parse_readiness_conditions(&x["setup"]["finished_condition"]),
+          timeout_ms: x["setup"]["finished_condition"]["timeout_ms"].as_u64().unwrap_or(DEFAULT_SETUP_TIMEOUT_MS),
+          interval_ms: x["setup"]["finished_condition"]["interval_ms"].as_u64().unwrap_or(DEFAULT_SETUP_INTERVAL_MS),
+          backoff: x["setup"]["finished_condition"]["backoff"].as_bool().unwrap_or(false),
+          max_interval_ms: x["setup"]["finished_condition"]["max_interval_ms"].as_u64().unwrap_or(DEFAULT_SETUP_MAX_INTERVAL_MS),
         },
       },
       cleanup: CleanupConfig {
-        cmd: x["cleanup"]["cmd"].to_string(),
+        cmd: x["cleanup"]["cmd"].as_str().unwrap_or_default().to_string(),
       },
       api_hostname: x["api_hostname"].to_string(),
+      request_timeout_ms: x["timeout_ms"].as_u64(),
     }).collect(),
     tasks: input["tasks"].entries().map(|(k, v)| (k.to_string(), Task {
       endpoint: v["endpoint"].to_string(),
       method: v["method"].to_string(),
       body: if v["body"].is_null() { None } else { Some(v["body"].to_string()) },
+      headers: if v["headers"].is_null() { None } else { Some(v["headers"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+      capture: if v["capture"].is_null() { None } else { Some(v["capture"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+      timeout_ms: v["timeout_ms"].as_u64(),
     })).collect(),
     tests: input["tests"].members().map(|test_chain| TestChain {
       name: test_chain["name"].to_string(),
+      stop_on_failure: test_chain["stop_on_failure"].as_bool().unwrap_or(false),
+      tags: parse_tags(&[&test_chain["tags"]]),
       defaults: if test_chain["defaults"].is_null() { None } else { Some(PartialTest {
         cookies: if test_chain["defaults"]["cookies"].is_null() { None } else { Some(test_chain["defaults"]["cookies"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+        headers: if test_chain["defaults"]["headers"].is_null() { None } else { Some(test_chain["defaults"]["headers"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
         before: if test_chain["defaults"]["before"].is_null() { None } else { Some(test_chain["defaults"]["before"].members().map(std::string::ToString::to_string).collect()) },
+        tags: if test_chain["defaults"]["tags"].is_null() { None } else { Some(parse_tags(&[&test_chain["defaults"]["tags"]])) },
+        timeout_ms: test_chain["defaults"]["timeout_ms"].as_u64(),
+        retry: parse_retry(&test_chain["defaults"]["retry"]),
       })},
       tests: test_chain["tests"].members().map(|test| Test {
         name: test["name"].to_string(),
@@ -115,23 +258,84 @@ fn parse_config_file(input: &jzon::JsonValue) -> ConfigFile {
         cookies: if test["cookies"].is_null() { 
           if test_chain["defaults"]["cookies"].is_null() { None } else { Some(test_chain["defaults"]["cookies"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) }
         } else { Some(test["cookies"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+        headers: if test["headers"].is_null() && test_chain["defaults"]["headers"].is_null() { None } else {
+          //unlike cookies, headers of the test are merged into the default headers, so a test can add a header without repeating the defaults
+          Some(test_chain["defaults"]["headers"].entries().chain(test["headers"].entries()).map(|(k, v)| (k.to_string(), v.to_string())).collect())
+        },
         before: if test["before"].is_null() { 
           if test_chain["defaults"]["before"].is_null() { None } else { Some(test_chain["defaults"]["before"].members().map(std::string::ToString::to_string).collect()) }
         } else { Some(test["before"].members().map(std::string::ToString::to_string).collect()) },
-        expected_outcome: TestOutcome {
-          status_code_equals: test["expected_outcome"]["status_code_equals"].as_usize(),
-          body_equals: if test["expected_outcome"]["body_equals"].is_null() { None } else { Some(test["expected_outcome"]["body_equals"].to_string()) },
-        },
+        capture: if test["capture"].is_null() { None } else { Some(test["capture"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+        expected_outcome: parse_test_outcome(&test["expected_outcome"]),
         skip: test["skip"].as_bool().unwrap_or(false),
+        tags: parse_tags(&[&test_chain["tags"], &test_chain["defaults"]["tags"], &test["tags"]]),
+        timeout_ms: test["timeout_ms"].as_u64().or(test_chain["defaults"]["timeout_ms"].as_u64()),
+        retry: parse_retry(&test["retry"]).or(parse_retry(&test_chain["defaults"]["retry"])),
       }).collect(),
     }).collect(),
   };
 }
 
-#[derive(Clone, Debug)]
-pub struct CliArgs {
-  pub config_to_run: Option<String>,
-  pub test_to_run: Option<String>,
+fn parse_test_outcome(input: &jzon::JsonValue) -> TestOutcome {
+  return TestOutcome {
+    status_code_equals: input["status_code_equals"].as_usize(),
+    body_equals: if input["body_equals"].is_null() { None } else { Some(input["body_equals"].to_string()) },
+    body_contains: if input["body_contains"].is_null() { None } else { Some(input["body_contains"].to_string()) },
+    headers_equal: if input["headers_equal"].is_null() { None } else { Some(input["headers_equal"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+    headers_contain: if input["headers_contain"].is_null() { None } else { Some(input["headers_contain"].entries().map(|(k, v)| (k.to_string(), v.to_string())).collect()) },
+    headers_absent: if input["headers_absent"].is_null() { None } else { Some(input["headers_absent"].members().map(std::string::ToString::to_string).collect()) },
+    assertions: if input["assertions"].is_null() { None } else { Some(input["assertions"].members().map(|x| Assertion {
+      path: x["path"].to_string(),
+      operator: AssertionOperator::parse(x["operator"].as_str().unwrap_or_default()).unwrap_or(AssertionOperator::Equals),
+      value: if x["value"].is_null() { None } else { Some(x["value"].dump()) },
+    }).collect()) },
+    max_duration_ms: input["max_duration_ms"].as_u64(),
+    body_matches_snapshot: parse_snapshot(&input["body_matches_snapshot"]),
+    body_matches_schema: if input["body_matches_schema"].is_null() { None } else { Some(input["body_matches_schema"].dump()) },
+  };
+}
+
+//true records the body as is, an object can list paths to redact
+fn parse_snapshot(input: &jzon::JsonValue) -> Option<Snapshot> {
+  if input.is_object() {
+    return Some(Snapshot { redact: input["redact"].members().map(std::string::ToString::to_string).collect() });
+  }
+  return if input.as_bool().unwrap_or(false) { Some(Snapshot::default()) } else { None };
+}
+
+fn parse_retry(input: &jzon::JsonValue) -> Option<Retry> {
+  if input.is_null() {
+    return None;
+  }
+  return Some(Retry {
+    attempts: input["attempts"].as_usize().unwrap_or(1),
+    delay_ms: input["delay_ms"].as_u64().unwrap_or(0),
+    until: if input["until"].is_null() { None } else { Some(parse_test_outcome(&input["until"])) },
+  });
+}
+
+//Keys of a finished_condition that arent conditions, like timeout_ms, are skipped
+//Every object in all and any is a group of conditions that all have to be met
+fn parse_readiness_conditions(input: &jzon::JsonValue) -> Vec<ReadinessCondition> {
+  return input.entries().filter_map(|(key, value)| match key {
+    "endpoint_reachable" => Some(ReadinessCondition::EndpointReachable(value.to_string())),
+    "endpoint_status" => Some(ReadinessCondition::EndpointStatus {
+      endpoint: value["endpoint"].to_string(),
+      status_code: value["status_code"].as_u16().unwrap_or(200),
+    }),
+    "endpoint_body_contains" => Some(ReadinessCondition::EndpointBodyContains {
+      endpoint: value["endpoint"].to_string(),
+      text: value["text"].to_string(),
+    }),
+    "tcp_port_open" => Some(ReadinessCondition::TcpPortOpen(value.to_string())),
+    "command_succeeds" => Some(ReadinessCondition::CommandSucceeds(value.to_string())),
+    "file_exists" => Some(ReadinessCondition::FileExists(value.to_string())),
+    "log_line_matches" => Some(ReadinessCondition::LogLineMatches(value.to_string())),
+    "delay_ms" => Some(ReadinessCondition::DelayMs(value.as_u64().unwrap_or_default())),
+    "all" => Some(ReadinessCondition::All(value.members().map(|x| ReadinessCondition::All(parse_readiness_conditions(x))).collect())),
+    "any" => Some(ReadinessCondition::Any(value.members().map(|x| ReadinessCondition::All(parse_readiness_conditions(x))).collect())),
+    _ => None,
+  }).collect();
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -148,6 +352,8 @@ pub struct Config {
   pub setup: SetupConfig,
   pub cleanup: CleanupConfig,
   pub api_hostname: String,
+  //timeout of the requests of tests and tasks that dont set their own, falls back to --request-timeout
+  pub request_timeout_ms: Option<u64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
@@ -158,12 +364,38 @@ pub struct CleanupConfig {
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct SetupConfig {
   pub cmd: String,
+  //runs after cmd and keeps running until cleanup, its output goes into log_file
+  pub background_cmd: String,
+  pub log_file: String,
   pub finished_condition: SetupFinishedCondition,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct SetupFinishedCondition {
-  pub endpoint_reachable: String,
+  pub conditions: Vec<ReadinessCondition>,
+  pub timeout_ms: u64,
+  pub interval_ms: u64,
+  pub backoff: bool,
+  pub max_interval_ms: u64,
+}
+
+//The setup is finished once all conditions are met
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub enum ReadinessCondition {
+  //any HTTP response counts, even errors
+  EndpointReachable(String),
+  EndpointStatus { endpoint: String, status_code: u16 },
+  EndpointBodyContains { endpoint: String, text: String },
+  //host:port or just a port on the host of api_hostname
+  TcpPortOpen(String),
+  CommandSucceeds(String),
+  FileExists(String),
+  //regular expression matched against each line the setup cmd printed
+  LogLineMatches(String),
+  //time since the setup cmd started
+  DelayMs(u64),
+  All(Vec<ReadinessCondition>),
+  Any(Vec<ReadinessCondition>),
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
@@ -171,19 +403,29 @@ pub struct Task {
   pub endpoint: String,
   pub method: String,
   pub body: Option<String>,
+  pub headers: Option<LinkedHashMap<String, String>>,
+  pub capture: Option<LinkedHashMap<String, String>>,
+  pub timeout_ms: Option<u64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct TestChain {
   pub name: String,
   pub defaults: Option<PartialTest>,
+  //skip the remaining tests of the chain once one failed, for chains where every test depends on the ones before
+  pub stop_on_failure: bool,
+  pub tags: Vec<String>,
   pub tests: LinkedHashSet<Test>,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct PartialTest {
   pub cookies: Option<LinkedHashMap<String, String>>,
+  pub headers: Option<LinkedHashMap<String, String>>,
   pub before: Option<LinkedHashSet<String>>,
+  pub tags: Option<Vec<String>>,
+  pub timeout_ms: Option<u64>,
+  pub retry: Option<Retry>,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
@@ -193,15 +435,58 @@ pub struct Test {
   pub method: String,
   pub body: Option<String>,
   pub cookies: Option<LinkedHashMap<String, String>>,
+  pub headers: Option<LinkedHashMap<String, String>>,
   pub before: Option<LinkedHashSet<String>>,
+  pub capture: Option<LinkedHashMap<String, String>>,
   pub expected_outcome: TestOutcome,
   pub skip: bool,
+  //the tags of the test, its defaults and its test chain, used by --tags and --exclude-tags
+  pub tags: Vec<String>,
+  //the timeout of the test or its defaults, if neither sets one the one of the config is used
+  pub timeout_ms: Option<u64>,
+  //the retry of the test or its defaults, --rerun-failed sets one for tests without
+  pub retry: Option<Retry>,
+}
+
+//How often a test is sent again, for endpoints that are eventually consistent
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct Retry {
+  //including the first one
+  pub attempts: usize,
+  pub delay_ms: u64,
+  //retrying stops once the response matches this, defaults to the expected_outcome
+  pub until: Option<TestOutcome>,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Eq, Default)]
 pub struct TestOutcome {
   pub status_code_equals: Option<usize>,
   pub body_equals: Option<String>,
+  pub body_contains: Option<String>,
+  pub headers_equal: Option<LinkedHashMap<String, String>>,
+  pub headers_contain: Option<LinkedHashMap<String, String>>,
+  pub headers_absent: Option<LinkedHashSet<String>>,
+  pub assertions: Option<Vec<Assertion>>,
+  //how long the response may take, in the actual outcome how long it took
+  pub max_duration_ms: Option<u64>,
+  pub body_matches_snapshot: Option<Snapshot>,
+  //json schema the body has to match, schema files are already loaded into it, in the actual outcome the body that didnt
+  pub body_matches_schema: Option<String>,
+}
+
+//The body is compared against the one recorded on the first run, see snapshot.rs
+#[derive(Clone, Debug, PartialEq, Hash, Eq, Default)]
+pub struct Snapshot {
+  //paths of volatile values like timestamps, they match any value
+  pub redact: Vec<String>,
+}
+
+//value is stored as json, so it can be compared against any json value in the response body
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct Assertion {
+  pub path: String,
+  pub operator: AssertionOperator,
+  pub value: Option<String>,
 }
 
 #[cfg(test)]
@@ -209,6 +494,7 @@ mod unit_test {
   use super::*;
 
   #[test]
+  #[allow(clippy::too_many_lines)]
   fn parse_config_file() {
     let config_file = ConfigFile {
       configs: [Config {
@@ -216,41 +502,77 @@ mod unit_test {
         description: "tests for the rest api of the backend".to_string(),
         setup: SetupConfig {
           cmd: "curl -O https://raw.githubusercontent.com/T-x-T/TxTs-Treasury/4b0cb752581eb58f20900bdaccb0caf3f0f6ddf5/docker-compose.yml && docker-compose up -d".to_string(),
+          background_cmd: String::new(),
+          log_file: String::new(),
           finished_condition: SetupFinishedCondition {
-            endpoint_reachable: "/api/v1".to_string(),
+            conditions: vec![
+              ReadinessCondition::EndpointReachable("/api/v1".to_string()),
+              ReadinessCondition::Any(vec![
+                ReadinessCondition::All(vec![ReadinessCondition::EndpointStatus { endpoint: "/api/v1/health".to_string(), status_code: 200 }]),
+                ReadinessCondition::All(vec![ReadinessCondition::LogLineMatches("listening on port \\d+".to_string()), ReadinessCondition::DelayMs(1000)]),
+              ]),
+            ],
+            timeout_ms: 120_000,
+            interval_ms: 500,
+            backoff: true,
+            max_interval_ms: DEFAULT_SETUP_MAX_INTERVAL_MS,
           }
         },
         cleanup: CleanupConfig {
           cmd: "docker-compose down && rm docker-compose.yml".to_string(),
         },
         api_hostname: "http://localhost:4000".to_string(),
+        request_timeout_ms: Some(10_000),
       }].into_iter().collect(),
       tasks: vec![("login_as_admin".to_string(), Task {
         endpoint: "/api/v1/login".to_string(),
         method: "POST".to_string(),
         body: Some(r#"{"name":"admin","secret":"changeme"}"#.to_string()),
+        headers: Some(vec![("Accept".to_string(), "application/json".to_string())].into_iter().collect()),
+        capture: None,
+        timeout_ms: None,
       })].into_iter().collect(),
       tests: [
         TestChain {
           name: "currency".to_string(),
           defaults: None,
+          stop_on_failure: false,
+          tags: vec!["smoke".to_string()],
           tests: [Test {
             name: "retrieval of all currencies works".to_string(),
 					  endpoint: "/api/v1/currencies/all".to_string(),
 					  method: "GET".to_string(),
             body: None,
             cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+            headers: None,
             before: Some(["login_as_admin".to_string()].into_iter().collect()),
+            capture: Some(vec![("euro_id".to_string(), "$[0].id".to_string())].into_iter().collect()),
             expected_outcome: TestOutcome {
               status_code_equals: Some(200),
               body_equals: Some(r#"[{"id":0,"name":"Euro","minor_in_mayor":100,"symbol":"€"},{"id":1,"name":"USD","minor_in_mayor":100,"symbol":"$"}]"#.to_string()),
+              body_contains: None,
+              headers_equal: None,
+              headers_contain: Some(vec![("content-type".to_string(), "application/json".to_string())].into_iter().collect()),
+              headers_absent: Some(["set-cookie".to_string()].into_iter().collect()),
+              assertions: Some(vec![
+                Assertion { path: "$.length".to_string(), operator: AssertionOperator::Gt, value: Some("0".to_string()) },
+                Assertion { path: "$[0].id".to_string(), operator: AssertionOperator::TypeIs, value: Some(r#""integer""#.to_string()) },
+              ]),
+              max_duration_ms: Some(1000),
+              body_matches_snapshot: None,
+              body_matches_schema: None,
             },
             skip: false,
+            tags: vec!["smoke".to_string()],
+            timeout_ms: None,
+            retry: None,
           }].into_iter().collect(),
         },
         TestChain {
           name: "recipients".to_string(),
           defaults: None,
+          stop_on_failure: true,
+          tags: Vec::new(),
           tests: [
             Test {
               name: "retrieval of all recipients works".to_string(),
@@ -258,12 +580,25 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: None,
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: Some(r#"{"type":"array","items":{"type":"object","required":["id","name"]}}"#.to_string()),
               },
               skip: false,
+              tags: Vec::new(),
+              timeout_ms: None,
+              retry: None,
             },
             Test {
               name: "post of new recipient returns 200".to_string(),
@@ -271,12 +606,25 @@ mod unit_test {
               method: "POST".to_string(),
               body: Some(r#"{"name":"test"}"#.to_string()),
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: None,
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: None,
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: Some(Snapshot { redact: vec!["$.id".to_string()] }),
+                body_matches_schema: None,
               },
               skip: false,
+              tags: Vec::new(),
+              timeout_ms: None,
+              retry: None,
             },
             Test {
               name: "new recipient created correctly".to_string(),
@@ -284,12 +632,25 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: None,
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]},{"id":1,"name":"test","user_id":0,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: Vec::new(),
+              timeout_ms: None,
+              retry: None,
             },
             Test {
               name: "updating name of recipient returns 200".to_string(),
@@ -297,12 +658,25 @@ mod unit_test {
               method: "PUT".to_string(),
               body: Some(r#"{"name":"edited test"}"#.to_string()),
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: None,
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: None,
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: Vec::new(),
+              timeout_ms: None,
+              retry: None,
             },
             Test {
               name: "updated recipient name saved correctly".to_string(),
@@ -310,20 +684,39 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: None,
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]},{"id":1,"name":"edited test","user_id":0,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: Vec::new(),
+              timeout_ms: None,
+              retry: None,
             },
           ].into_iter().collect(),
         },
         TestChain {
           name: "recipients_with_defaults".to_string(),
+          stop_on_failure: false,
+          tags: Vec::new(),
           defaults: Some(PartialTest {
             cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+            headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string())].into_iter().collect()),
             before: Some(["login_as_admin".to_string()].into_iter().collect()),
+            tags: Some(vec!["recipients".to_string()]),
+            timeout_ms: Some(5000),
+            retry: Some(Retry { attempts: 3, delay_ms: 500, until: None }),
           }),
           tests: [
             Test {
@@ -332,12 +725,25 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string()), ("Accept-Language".to_string(), "en".to_string())].into_iter().collect()),
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: vec!["recipients".to_string(), "smoke".to_string()],
+              timeout_ms: Some(2000),
+              retry: Some(Retry { attempts: 5, delay_ms: 1000, until: Some(TestOutcome { status_code_equals: Some(200), ..TestOutcome::default() }) }),
             },
             Test {
               name: "post of new recipient returns 200".to_string(),
@@ -345,12 +751,25 @@ mod unit_test {
               method: "POST".to_string(),
               body: Some(r#"{"name":"test"}"#.to_string()),
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string())].into_iter().collect()),
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: None,
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: vec!["recipients".to_string()],
+              timeout_ms: Some(5000),
+              retry: Some(Retry { attempts: 3, delay_ms: 500, until: None }),
             },
             Test {
               name: "new recipient created correctly".to_string(),
@@ -358,12 +777,25 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string())].into_iter().collect()),
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]},{"id":1,"name":"test","user_id":0,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: vec!["recipients".to_string()],
+              timeout_ms: Some(5000),
+              retry: Some(Retry { attempts: 3, delay_ms: 500, until: None }),
             },
             Test {
               name: "updating name of recipient returns 200".to_string(),
@@ -371,12 +803,25 @@ mod unit_test {
               method: "PUT".to_string(),
               body: Some(r#"{"name":"edited test"}"#.to_string()),
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string())].into_iter().collect()),
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: None,
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: vec!["recipients".to_string()],
+              timeout_ms: Some(5000),
+              retry: Some(Retry { attempts: 3, delay_ms: 500, until: None }),
             },
             Test {
               name: "updated recipient name saved correctly".to_string(),
@@ -384,12 +829,25 @@ mod unit_test {
               method: "GET".to_string(),
               body: None,
               cookies: Some(vec![("accessToken".to_string(), "$login_as_admin.accessToken".to_string())].into_iter().collect()),
+              headers: Some(vec![("Authorization".to_string(), "Bearer $login_as_admin.accessToken".to_string())].into_iter().collect()),
               before: Some(["login_as_admin".to_string()].into_iter().collect()),
+              capture: None,
               expected_outcome: TestOutcome {
                 status_code_equals: Some(200),
                 body_equals: Some(r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]},{"id":1,"name":"edited test","user_id":0,"tag_ids":[]}]"#.to_string()),
+                body_contains: None,
+                headers_equal: None,
+                headers_contain: None,
+                headers_absent: None,
+                assertions: None,
+                max_duration_ms: None,
+                body_matches_snapshot: None,
+                body_matches_schema: None,
               },
               skip: false,
+              tags: vec!["recipients".to_string()],
+              timeout_ms: Some(5000),
+              retry: Some(Retry { attempts: 3, delay_ms: 500, until: None }),
             },
           ].into_iter().collect(),
         },
@@ -0,0 +1,61 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+//Output of a test chain that runs on a worker thread is collected here and printed at once when the chain finished, so chains dont interleave
+thread_local! {
+  static BUFFER: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+//Like print!, but goes into the buffer of the current thread if there is one
+macro_rules! output {
+  ($($arg:tt)*) => { $crate::output::write(&format!($($arg)*)) };
+}
+
+//Like println!, but goes into the buffer of the current thread if there is one
+macro_rules! outputln {
+  () => { $crate::output::write("\n") };
+  ($($arg:tt)*) => { $crate::output::write(&format!("{}\n", format_args!($($arg)*))) };
+}
+
+pub(crate) use {output, outputln};
+
+pub fn write(text: &str) {
+  let buffered = BUFFER.with_borrow_mut(|buffer| {
+    if let Some(buffer) = buffer.as_mut() {
+      buffer.push_str(text);
+      return true;
+    }
+    return false;
+  });
+
+  if !buffered {
+    print!("{text}");
+    let _ = io::stdout().flush();
+  }
+}
+
+//Runs f with everything it outputs going into a buffer and returns the buffer together with the result
+pub fn buffered<T>(f: impl FnOnce() -> T) -> (T, String) {
+  BUFFER.with_borrow_mut(|buffer| *buffer = Some(String::new()));
+  let result = f();
+  let output = BUFFER.with_borrow_mut(Option::take).unwrap_or_default();
+  return (result, output);
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn buffered_collects_output_of_the_current_thread() {
+    let (result, output) = buffered(|| {
+      output!("setting up... ");
+      outputln!("setup completed");
+      std::thread::spawn(|| outputln!("not buffered")).join().unwrap();
+      return 5;
+    });
+
+    assert_eq!(result, 5);
+    assert_eq!(output, "setting up... setup completed\n");
+  }
+}
@@ -0,0 +1,180 @@
+use std::{fs, process, thread};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::ReadinessCondition;
+
+//Everything about the running setup that conditions can check
+pub struct SetupContext<'a> {
+  pub api_hostname: &'a str,
+  pub started: Instant,
+  //stdout and stderr of the setup cmd
+  pub output: &'a str,
+  //log of the background_cmd, which keeps growing while the setup waits
+  pub log_file: Option<&'a Path>,
+}
+
+//Returns Ok if all conditions are met, otherwise a description of the first one that isnt
+//timeout limits how long a single check like a request or command can block
+pub fn check_all(conditions: &[ReadinessCondition], context: &SetupContext, timeout: Duration) -> Result<(), String> {
+  for condition in conditions {
+    check(condition, context, timeout)?;
+  }
+  return Ok(());
+}
+
+pub fn check(condition: &ReadinessCondition, context: &SetupContext, timeout: Duration) -> Result<(), String> {
+  let is_met = match condition {
+    ReadinessCondition::EndpointReachable(endpoint) => get(context, endpoint, timeout).is_some(),
+    ReadinessCondition::EndpointStatus { endpoint, status_code } => get(context, endpoint, timeout).is_some_and(|x| x.status() == *status_code),
+    ReadinessCondition::EndpointBodyContains { endpoint, text } => get(context, endpoint, timeout).is_some_and(|x| x.into_string().unwrap_or_default().contains(text.as_str())),
+    ReadinessCondition::TcpPortOpen(address) => is_port_open(context, address, timeout),
+    ReadinessCondition::CommandSucceeds(cmd) => command_succeeds(cmd, timeout),
+    ReadinessCondition::FileExists(path) => fs::metadata(path).is_ok(),
+    ReadinessCondition::LogLineMatches(pattern) => {
+      let log = context.log_file.map(|x| fs::read_to_string(x).unwrap_or_default()).unwrap_or_default();
+      regex_lite::Regex::new(pattern).is_ok_and(|x| context.output.lines().chain(log.lines()).any(|line| x.is_match(line)))
+    },
+    ReadinessCondition::DelayMs(delay_ms) => context.started.elapsed() >= Duration::from_millis(*delay_ms),
+    ReadinessCondition::All(conditions) => return check_all(conditions, context, timeout),
+    ReadinessCondition::Any(conditions) => {
+      let errors: Vec<String> = conditions.iter().filter_map(|x| check(x, context, timeout).err()).collect();
+      if errors.len() < conditions.len() || conditions.is_empty() {
+        return Ok(());
+      }
+      return Err(format!("any of ({})", errors.join(" or ")));
+    },
+  };
+
+  if is_met {
+    return Ok(());
+  }
+  return Err(describe(condition));
+}
+
+fn describe(condition: &ReadinessCondition) -> String {
+  return match condition {
+    ReadinessCondition::EndpointReachable(endpoint) => format!("endpoint {endpoint} to be reachable"),
+    ReadinessCondition::EndpointStatus { endpoint, status_code } => format!("endpoint {endpoint} to respond with status {status_code}"),
+    ReadinessCondition::EndpointBodyContains { endpoint, text } => format!("response body of endpoint {endpoint} to contain {text}"),
+    ReadinessCondition::TcpPortOpen(address) => format!("tcp port {address} to be open"),
+    ReadinessCondition::CommandSucceeds(cmd) => format!("command {cmd} to succeed"),
+    ReadinessCondition::FileExists(path) => format!("file {path} to exist"),
+    ReadinessCondition::LogLineMatches(pattern) => format!("a line of the setup output to match {pattern}"),
+    ReadinessCondition::DelayMs(delay_ms) => format!("{delay_ms}ms to pass"),
+    ReadinessCondition::All(conditions) => conditions.iter().map(describe).collect::<Vec<String>>().join(" and "),
+    ReadinessCondition::Any(conditions) => format!("any of ({})", conditions.iter().map(describe).collect::<Vec<String>>().join(" or ")),
+  };
+}
+
+//Returns the response for every status code, only connection errors and timeouts return None
+fn get(context: &SetupContext, endpoint: &str, timeout: Duration) -> Option<ureq::Response> {
+  return match ureq::get(&format!("{}{endpoint}", context.api_hostname)).timeout(timeout).call() {
+    Ok(x) | Err(ureq::Error::Status(_, x)) => Some(x),
+    Err(_) => None,
+  };
+}
+
+fn is_port_open(context: &SetupContext, address: &str, timeout: Duration) -> bool {
+  let address = if address.chars().all(|x| x.is_ascii_digit()) { format!("{}:{address}", get_host(context.api_hostname)) } else { address.to_string() };
+  let Ok(socket_addresses) = address.to_socket_addrs() else { return false };
+  return socket_addresses.into_iter().any(|x| TcpStream::connect_timeout(&x, timeout).is_ok());
+}
+
+//http://localhost:4000/api becomes localhost
+fn get_host(api_hostname: &str) -> &str {
+  let without_scheme = api_hostname.split_once("://").map_or(api_hostname, |x| x.1);
+  let authority = without_scheme.split('/').next().unwrap_or_default();
+  if authority.starts_with('[') {
+    return authority.split_inclusive(']').next().unwrap_or_default();
+  }
+  return authority.split(':').next().unwrap_or_default();
+}
+
+//Commands that dont exit within timeout get killed and count as failed
+fn command_succeeds(cmd: &str, timeout: Duration) -> bool {
+  let Ok(mut child) = process::Command::new("sh")
+    .arg("-c")
+    .arg(cmd)
+    .stdout(process::Stdio::null())
+    .stderr(process::Stdio::null())
+    .spawn() else { return false };
+
+  let started = Instant::now();
+  loop {
+    match child.try_wait() {
+      Ok(Some(status)) => return status.success(),
+      Ok(None) if started.elapsed() < timeout => thread::sleep(Duration::from_millis(10)),
+      _ => {
+        let _ = child.kill();
+        let _ = child.wait();
+        return false;
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  fn context(output: &str) -> SetupContext<'_> {
+    return SetupContext {
+      api_hostname: "http://127.0.0.1:1",
+      started: Instant::now(),
+      output,
+      log_file: None,
+    };
+  }
+
+  #[test]
+  fn check_local_conditions() {
+    let context = context("starting\nlistening on port 4000\n");
+    let timeout = Duration::from_secs(1);
+
+    assert_eq!(check(&ReadinessCondition::CommandSucceeds("true".to_string()), &context, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::CommandSucceeds("exit 1".to_string()), &context, timeout), Err("command exit 1 to succeed".to_string()));
+    assert_eq!(check(&ReadinessCondition::FileExists("./Cargo.toml".to_string()), &context, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::LogLineMatches(r"^listening on port \d+$".to_string()), &context, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::LogLineMatches("ready".to_string()), &context, timeout), Err("a line of the setup output to match ready".to_string()));
+    assert_eq!(check(&ReadinessCondition::LogLineMatches("^version = ".to_string()), &SetupContext { log_file: Some(Path::new("./Cargo.toml")), ..context }, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::DelayMs(0), &context, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::DelayMs(60_000), &context, timeout), Err("60000ms to pass".to_string()));
+  }
+
+  #[test]
+  fn commands_that_dont_exit_in_time_fail() {
+    let started = Instant::now();
+    assert!(!command_succeeds("sleep 5", Duration::from_millis(50)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+  }
+
+  #[test]
+  fn check_combined_conditions() {
+    let context = context("");
+    let timeout = Duration::from_secs(1);
+    let met = ReadinessCondition::FileExists("./Cargo.toml".to_string());
+    let unmet = ReadinessCondition::FileExists("./missing".to_string());
+
+    assert_eq!(check(&ReadinessCondition::Any(vec![unmet.clone(), met.clone()]), &context, timeout), Ok(()));
+    assert_eq!(check(&ReadinessCondition::Any(vec![unmet.clone(), unmet.clone()]), &context, timeout), Err("any of (file ./missing to exist or file ./missing to exist)".to_string()));
+    assert_eq!(check_all(&[met.clone(), ReadinessCondition::All(vec![met, unmet])], &context, timeout), Err("file ./missing to exist".to_string()));
+  }
+
+  #[test]
+  fn get_host_of_api_hostname() {
+    assert_eq!(get_host("http://localhost:4000/api"), "localhost");
+    assert_eq!(get_host("https://example.com"), "example.com");
+    assert_eq!(get_host("http://[::1]:4000"), "[::1]");
+  }
+
+  #[test]
+  fn closed_port_and_unreachable_endpoint() {
+    let context = context("");
+    let timeout = Duration::from_millis(200);
+
+    assert_eq!(check(&ReadinessCondition::TcpPortOpen("1".to_string()), &context, timeout), Err("tcp port 1 to be open".to_string()));
+    assert_eq!(check(&ReadinessCondition::EndpointReachable("/health".to_string()), &context, timeout), Err("endpoint /health to be reachable".to_string()));
+  }
+}
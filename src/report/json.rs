@@ -0,0 +1,208 @@
+use std::fs;
+use std::time::Duration;
+use jzon::JsonValue;
+use linked_hash_map::LinkedHashMap;
+
+use crate::TestOutcome;
+use crate::test::TestFailure;
+use super::{ConfigResult, TestResult, TestStatus};
+
+//Writes every config, test chain and test with its status, timing, request, response and outcomes as a single json document
+//duration is how long the whole run took, with --jobs thats less than the durations of the test chains added up
+pub fn write(path: &str, config_results: &[ConfigResult], duration: Duration) -> std::io::Result<()> {
+  return fs::write(path, render(config_results, duration).pretty(2));
+}
+
+pub fn render(config_results: &[ConfigResult], duration: Duration) -> JsonValue {
+  let test_results = config_results.iter().flat_map(|x| x.test_chains.iter()).flat_map(|x| x.tests.iter());
+
+  return jzon::object! {
+    summary: {
+      total: test_results.clone().count(),
+      passed: test_results.clone().filter(|x| x.status == TestStatus::Passed).count(),
+      flaky: test_results.clone().filter(|x| x.is_flaky()).count(),
+      failed: test_results.clone().filter(|x| matches!(x.status, TestStatus::Failed(_))).count(),
+      skipped: test_results.clone().filter(|x| matches!(x.status, TestStatus::Skipped(_))).count(),
+      deselected: test_results.clone().filter(|x| matches!(x.status, TestStatus::Deselected(_))).count(),
+      duration_ms: milliseconds(duration),
+    },
+    configs: config_results.iter().map(|config| jzon::object! {
+      name: config.name.as_str(),
+      status: if config.has_failures() { "failed" } else { "passed" },
+      test_chains: config.test_chains.iter().map(|test_chain| jzon::object! {
+        name: test_chain.name.as_str(),
+        status: if test_chain.has_failures() { "failed" } else { "passed" },
+        duration_ms: milliseconds(test_chain.duration),
+        setup_error: test_chain.setup_error.as_deref(),
+        cleanup_error: test_chain.cleanup_error.as_deref(),
+        tests: test_chain.tests.iter().map(|test| (test, failure_of(test))).map(|(test, failure)| jzon::object! {
+          name: test.name.as_str(),
+          status: test.status.name(),
+          flaky: test.is_flaky(),
+          attempts: test.attempts,
+          duration_ms: milliseconds(test.duration),
+          latency_ms: test.latency.map(milliseconds),
+          request: {
+            method: test.method.as_str(),
+            url: test.url.as_str(),
+          },
+          response: {
+            status_code: test.response_status_code,
+          },
+          expected_outcome: outcome_to_json(&test.expected_outcome),
+          actual_outcome: failure.map(|x| outcome_to_json(&x.actual_outcome)),
+          body_diff: failure.map(|x| x.body_diff.clone()),
+          body_contains_diff: failure.map(|x| x.body_contains_diff.clone()),
+          failed_assertions: failure.map(|x| x.failed_assertions.clone()),
+          snapshot_diff: failure.map(|x| x.snapshot_diff.clone()),
+          schema_errors: failure.map(|x| x.schema_errors.clone()),
+          message: test.message(),
+        }).collect::<Vec<JsonValue>>(),
+      }).collect::<Vec<JsonValue>>(),
+    }).collect::<Vec<JsonValue>>(),
+  };
+}
+
+//Only fields that are set end up in the output, bodies that are valid json are embedded as json instead of as a string
+pub fn outcome_to_json(outcome: &TestOutcome) -> JsonValue {
+  let mut output = JsonValue::new_object();
+
+  if outcome.status_code_equals.is_some() {
+    output["status_code_equals"] = outcome.status_code_equals.into();
+  }
+  if outcome.body_equals.is_some() {
+    output["body_equals"] = body_to_json(outcome.body_equals.as_ref().unwrap());
+  }
+  if outcome.body_contains.is_some() {
+    output["body_contains"] = body_to_json(outcome.body_contains.as_ref().unwrap());
+  }
+  if outcome.headers_equal.is_some() {
+    output["headers_equal"] = string_map_to_json(outcome.headers_equal.as_ref().unwrap());
+  }
+  if outcome.headers_contain.is_some() {
+    output["headers_contain"] = string_map_to_json(outcome.headers_contain.as_ref().unwrap());
+  }
+  if outcome.headers_absent.is_some() {
+    output["headers_absent"] = outcome.headers_absent.as_ref().unwrap().iter().map(String::as_str).collect::<Vec<&str>>().into();
+  }
+  if outcome.assertions.is_some() {
+    output["assertions"] = outcome.assertions.as_ref().unwrap().iter().map(|x| {
+      let mut assertion = jzon::object! { path: x.path.as_str(), operator: x.operator.name() };
+      if x.value.is_some() {
+        assertion["value"] = body_to_json(x.value.as_ref().unwrap());
+      }
+      assertion
+    }).collect::<Vec<JsonValue>>().into();
+  }
+
+  if outcome.max_duration_ms.is_some() {
+    output["max_duration_ms"] = outcome.max_duration_ms.into();
+  }
+  if outcome.body_matches_schema.is_some() {
+    output["body_matches_schema"] = body_to_json(outcome.body_matches_schema.as_ref().unwrap());
+  }
+  if outcome.body_matches_snapshot.is_some() {
+    let redact = &outcome.body_matches_snapshot.as_ref().unwrap().redact;
+    output["body_matches_snapshot"] = if redact.is_empty() { true.into() } else { jzon::object! { redact: redact.clone() } };
+  }
+
+  return output;
+}
+
+fn string_map_to_json(map: &LinkedHashMap<String, String>) -> JsonValue {
+  let mut output = JsonValue::new_object();
+  for (key, value) in map {
+    output[key.as_str()] = value.as_str().into();
+  }
+  return output;
+}
+
+fn body_to_json(body: &str) -> JsonValue {
+  return jzon::parse(body).unwrap_or_else(|_| body.into());
+}
+
+//Tests that failed without a response have no actual outcome or diffs, only a message
+fn failure_of(test: &TestResult) -> Option<&TestFailure> {
+  return match &test.status {
+    TestStatus::Failed(failure) if failure.error.is_none() => Some(failure),
+    _ => None,
+  };
+}
+
+fn milliseconds(duration: Duration) -> u64 {
+  return u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+  use crate::report::TestChainResult;
+
+  #[test]
+  fn render_failed_test() {
+    let config_results = vec![ConfigResult {
+      name: "backend".to_string(),
+      test_chains: vec![TestChainResult {
+        name: "recipients".to_string(),
+        duration: Duration::from_millis(1500),
+        tests: vec![TestResult {
+          name: "retrieval of all recipients works".to_string(),
+          status: TestStatus::Failed(TestFailure {
+            actual_outcome: TestOutcome {
+              status_code_equals: Some(500),
+              ..TestOutcome::default()
+            },
+            response_body: "[]".to_string(),
+            ..TestFailure::default()
+          }),
+          duration: Duration::from_millis(12),
+          method: "GET".to_string(),
+          url: "http://localhost:4000/api/v1/recipients/all".to_string(),
+          response_status_code: Some(500),
+          expected_outcome: TestOutcome {
+            status_code_equals: Some(200),
+            body_equals: Some(r#"[{"id":0}]"#.to_string()),
+            ..TestOutcome::default()
+          },
+          attempts: 1,
+          latency: Some(Duration::from_millis(9)),
+        }],
+        setup_error: None,
+        cleanup_error: None,
+      }],
+    }];
+
+    assert_eq!(render(&config_results, Duration::from_millis(1600)).dump(), jzon::parse(r#"{
+      "summary": {"total": 1, "passed": 0, "flaky": 0, "failed": 1, "skipped": 0, "deselected": 0, "duration_ms": 1600},
+      "configs": [{
+        "name": "backend",
+        "status": "failed",
+        "test_chains": [{
+          "name": "recipients",
+          "status": "failed",
+          "duration_ms": 1500,
+          "setup_error": null,
+          "cleanup_error": null,
+          "tests": [{
+            "name": "retrieval of all recipients works",
+            "status": "failed",
+            "flaky": false,
+            "attempts": 1,
+            "duration_ms": 12,
+            "latency_ms": 9,
+            "request": {"method": "GET", "url": "http://localhost:4000/api/v1/recipients/all"},
+            "response": {"status_code": 500},
+            "expected_outcome": {"status_code_equals": 200, "body_equals": [{"id": 0}]},
+            "actual_outcome": {"status_code_equals": 500},
+            "body_diff": [],
+            "body_contains_diff": [],
+            "failed_assertions": [],
+            "snapshot_diff": [],
+            "schema_errors": [],
+            "message": "response status code of 500 didnt match expected outcome 200\nresponse body was []\n"
+          }]
+        }]
+      }]
+    }"#).unwrap().dump());
+  }
+}
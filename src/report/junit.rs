@@ -0,0 +1,193 @@
+use std::fs;
+use std::time::Duration;
+
+use super::{ConfigResult, TestStatus};
+
+//Writes one testsuite per config and test chain pair and one testcase per test, in the format most CI systems can display
+pub fn write(path: &str, config_results: &[ConfigResult]) -> std::io::Result<()> {
+  return fs::write(path, render(config_results));
+}
+
+pub fn render(config_results: &[ConfigResult]) -> String {
+  let test_chains = config_results.iter().flat_map(|config| config.test_chains.iter().map(move |test_chain| (config, test_chain)));
+
+  let mut output_parts: Vec<String> = Vec::new();
+  let mut total_tests = 0;
+  let mut total_failures = 0;
+  let mut total_skipped = 0;
+  let mut total_errors = 0;
+  let mut total_duration = Duration::ZERO;
+
+  for (config, test_chain) in test_chains {
+    let failures = test_chain.count(|x| matches!(x, TestStatus::Failed(_)));
+    //junit has no deselected state, so those are reported as skipped with the filter as message
+    let skipped = test_chain.count(|x| matches!(x, TestStatus::Skipped(_) | TestStatus::Deselected(_)));
+    let errors = usize::from(test_chain.setup_error.is_some()) + usize::from(test_chain.cleanup_error.is_some());
+    total_tests += test_chain.tests.len() + errors;
+    total_failures += failures;
+    total_skipped += skipped;
+    total_errors += errors;
+    total_duration += test_chain.duration;
+
+    output_parts.push(format!(
+      "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\" skipped=\"{skipped}\" time=\"{}\">\n",
+      escape(&format!("{}: {}", config.name, test_chain.name)),
+      test_chain.tests.len() + errors,
+      seconds(test_chain.duration),
+    ));
+
+    //a failed setup is reported as an error of a testcase named setup, as CI systems only display testcases
+    if test_chain.setup_error.is_some() {
+      output_parts.push(format!(
+        "    <testcase name=\"setup\" classname=\"{}\" time=\"{}\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+        escape(&format!("{}.{}", config.name, test_chain.name)),
+        seconds(test_chain.duration),
+        escape(test_chain.setup_error.as_ref().unwrap()),
+      ));
+    }
+
+    for test in &test_chain.tests {
+      let test_case = format!(
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+        escape(&test.name),
+        escape(&format!("{}.{}", config.name, test_chain.name)),
+        seconds(test.duration),
+      );
+
+      match &test.status {
+        //flakyFailure is how surefire marks tests that passed on a rerun, most CI systems understand it
+        TestStatus::Passed if test.is_flaky() => output_parts.push(format!("{test_case}>\n      <flakyFailure message=\"passed after {} attempts\"/>\n    </testcase>\n", test.attempts)),
+        TestStatus::Passed => output_parts.push(format!("{test_case}/>\n")),
+        TestStatus::Skipped(reason) | TestStatus::Deselected(reason) => output_parts.push(format!("{test_case}>\n      <skipped message=\"{}\"/>\n    </testcase>\n", escape(reason))),
+        TestStatus::Failed(_) => {
+          let message = test.message().unwrap_or_default();
+          output_parts.push(format!(
+            "{test_case}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+            escape(message.lines().find(|x| !x.trim().is_empty()).unwrap_or_default().trim()),
+            escape(&message),
+          ));
+        },
+      }
+    }
+
+    if test_chain.cleanup_error.is_some() {
+      output_parts.push(format!(
+        "    <testcase name=\"cleanup\" classname=\"{}\" time=\"0.000\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+        escape(&format!("{}.{}", config.name, test_chain.name)),
+        escape(test_chain.cleanup_error.as_ref().unwrap()),
+      ));
+    }
+
+    output_parts.push(String::from("  </testsuite>\n"));
+  }
+
+  return format!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"trest\" tests=\"{total_tests}\" failures=\"{total_failures}\" errors=\"{total_errors}\" skipped=\"{total_skipped}\" time=\"{}\">\n{}</testsuites>\n",
+    seconds(total_duration),
+    output_parts.concat(),
+  );
+}
+
+fn seconds(duration: Duration) -> String {
+  return format!("{:.3}", duration.as_secs_f64());
+}
+
+fn escape(input: &str) -> String {
+  return input
+    .chars()
+    .filter(|x| !x.is_control() || matches!(x, '\n' | '\t' | '\r'))
+    .map(|x| match x {
+      '&' => String::from("&amp;"),
+      '<' => String::from("&lt;"),
+      '>' => String::from("&gt;"),
+      '"' => String::from("&quot;"),
+      '\'' => String::from("&apos;"),
+      x => x.to_string(),
+    })
+    .collect();
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+  use crate::TestOutcome;
+  use crate::report::{TestChainResult, TestResult};
+  use crate::test::TestFailure;
+
+  fn test_result(name: &str, status: TestStatus, duration: Duration) -> TestResult {
+    return TestResult {
+      name: name.to_string(),
+      status,
+      duration,
+      method: "GET".to_string(),
+      url: "http://localhost:4000/api/v1/recipients/all".to_string(),
+      response_status_code: None,
+      expected_outcome: TestOutcome::default(),
+      attempts: 1,
+      latency: None,
+    };
+  }
+
+  #[test]
+  fn render_all_statuses() {
+    let config_results = vec![ConfigResult {
+      name: "backend".to_string(),
+      test_chains: vec![TestChainResult {
+        name: "recipients".to_string(),
+        duration: Duration::from_millis(1500),
+        tests: vec![
+          test_result("works", TestStatus::Passed, Duration::from_millis(20)),
+          TestResult { attempts: 3, ..test_result("eventually works", TestStatus::Passed, Duration::from_millis(40)) },
+          TestResult {
+            expected_outcome: TestOutcome { status_code_equals: Some(200), ..TestOutcome::default() },
+            ..test_result("<broken>", TestStatus::Failed(TestFailure {
+              actual_outcome: TestOutcome { status_code_equals: Some(500), ..TestOutcome::default() },
+              response_body: "<html></html>".to_string(),
+              ..TestFailure::default()
+            }), Duration::from_millis(5))
+          },
+          test_result("later", TestStatus::Skipped("test chain stopped after <broken> failed".to_string()), Duration::ZERO),
+          test_result("slow", TestStatus::Deselected("deselected by --exclude-tags=slow".to_string()), Duration::ZERO),
+        ],
+        setup_error: None,
+        cleanup_error: None,
+      }, TestChainResult {
+        name: "currencies".to_string(),
+        duration: Duration::from_secs(2),
+        tests: Vec::new(),
+        setup_error: Some("setup didnt finish within 2000ms".to_string()),
+        cleanup_error: Some("cleanup cmd failed with exit status: 1: no such container".to_string()),
+      }],
+    }];
+
+    assert_eq!(render(&config_results), r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="trest" tests="7" failures="1" errors="2" skipped="2" time="3.500">
+  <testsuite name="backend: recipients" tests="5" failures="1" errors="0" skipped="2" time="1.500">
+    <testcase name="works" classname="backend.recipients" time="0.020"/>
+    <testcase name="eventually works" classname="backend.recipients" time="0.040">
+      <flakyFailure message="passed after 3 attempts"/>
+    </testcase>
+    <testcase name="&lt;broken&gt;" classname="backend.recipients" time="0.005">
+      <failure message="response status code of 500 didnt match expected outcome 200">response status code of 500 didnt match expected outcome 200
+response body was &lt;html&gt;&lt;/html&gt;
+</failure>
+    </testcase>
+    <testcase name="later" classname="backend.recipients" time="0.000">
+      <skipped message="test chain stopped after &lt;broken&gt; failed"/>
+    </testcase>
+    <testcase name="slow" classname="backend.recipients" time="0.000">
+      <skipped message="deselected by --exclude-tags=slow"/>
+    </testcase>
+  </testsuite>
+  <testsuite name="backend: currencies" tests="2" failures="0" errors="2" skipped="0" time="2.000">
+    <testcase name="setup" classname="backend.currencies" time="2.000">
+      <error message="setup didnt finish within 2000ms"/>
+    </testcase>
+    <testcase name="cleanup" classname="backend.currencies" time="0.000">
+      <error message="cleanup cmd failed with exit status: 1: no such container"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#);
+  }
+}
@@ -0,0 +1,98 @@
+pub mod junit;
+pub mod json;
+
+use std::time::Duration;
+
+use crate::{test, TestOutcome};
+use crate::test::TestFailure;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigResult {
+  pub name: String,
+  pub test_chains: Vec<TestChainResult>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestChainResult {
+  pub name: String,
+  pub duration: Duration,
+  pub tests: Vec<TestResult>,
+  //set if the setup didnt finish, in that case none of the tests ran
+  pub setup_error: Option<String>,
+  //set if the cleanup cmd failed, the tests ran but whatever the setup started might still be around
+  pub cleanup_error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestResult {
+  pub name: String,
+  pub status: TestStatus,
+  pub duration: Duration,
+  pub method: String,
+  pub url: String,
+  pub response_status_code: Option<u16>,
+  pub expected_outcome: TestOutcome,
+  //how often the request was sent because of retry or --rerun-failed, 0 if the test didnt run
+  pub attempts: usize,
+  //how long the response of the last attempt took, None if there was no response
+  pub latency: Option<Duration>,
+}
+
+impl TestResult {
+  //A test that only passed after retrying
+  pub fn is_flaky(&self) -> bool {
+    return self.status == TestStatus::Passed && self.attempts > 1;
+  }
+
+  //Why the test failed or didnt run, without colors so it can be written into report files
+  pub fn message(&self) -> Option<String> {
+    return match &self.status {
+      TestStatus::Passed => None,
+      TestStatus::Failed(failure) => Some(test::describe_failure(failure, &self.expected_outcome, false)),
+      TestStatus::Skipped(reason) | TestStatus::Deselected(reason) => Some(reason.clone()),
+    };
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum TestStatus {
+  Passed,
+  //contains everything that didnt match, reports and the terminal output are rendered from it
+  Failed(TestFailure),
+  //contains why the test didnt run
+  Skipped(String),
+  //contains which --tags or --exclude-tags filtered the test out
+  Deselected(String),
+}
+
+impl TestStatus {
+  pub fn name(&self) -> &'static str {
+    return match self {
+      TestStatus::Passed => "passed",
+      TestStatus::Failed(_) => "failed",
+      TestStatus::Skipped(_) => "skipped",
+      TestStatus::Deselected(_) => "deselected",
+    };
+  }
+}
+
+impl ConfigResult {
+  pub fn has_failures(&self) -> bool {
+    return self.test_chains.iter().any(TestChainResult::has_failures);
+  }
+
+  pub fn has_setup_errors(&self) -> bool {
+    return self.test_chains.iter().any(|x| x.setup_error.is_some());
+  }
+}
+
+impl TestChainResult {
+  pub fn has_failures(&self) -> bool {
+    return self.setup_error.is_some() || self.cleanup_error.is_some() || self.tests.iter().any(|x| matches!(x.status, TestStatus::Failed(_)));
+  }
+
+  pub fn count(&self, status: fn(&TestStatus) -> bool) -> usize {
+    return self.tests.iter().filter(|x| status(&x.status)).count();
+  }
+}
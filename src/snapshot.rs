@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use jzon::JsonValue;
+
+use crate::json_path;
+
+//The snapshots of a config file are kept in a directory next to it, like test/sample.snapshots for test/sample.json
+pub fn directory(config_path: &str) -> PathBuf {
+  let config_path = Path::new(config_path);
+  return config_path.with_file_name(format!("{}.snapshots", config_path.file_stem().unwrap_or_default().to_string_lossy()));
+}
+
+//Every test has its own file in a directory per test chain, shared by all configs since they test the same api
+pub fn path(directory: &Path, test_chain_name: &str, test_name: &str) -> PathBuf {
+  return directory.join(file_name(test_chain_name)).join(format!("{}.json", file_name(test_name)));
+}
+
+//Returns None if the test has no snapshot yet
+pub fn read(path: &Path) -> Result<Option<JsonValue>, String> {
+  if !path.exists() {
+    return Ok(None);
+  }
+  let snapshot = fs::read_to_string(path).map_err(|e| format!("failed to read snapshot {}: {e}", path.display()))?;
+  return jzon::parse(&snapshot).map(Some).map_err(|e| format!("snapshot {} isnt valid json: {e}", path.display()));
+}
+
+//The snapshot is written to a temporary file next to it first and then renamed into place,
+//so workers of other configs reading the same snapshot never see a half written file
+pub fn write(path: &Path, snapshot: &JsonValue) -> Result<(), String> {
+  static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(|e| format!("failed to create snapshot directory {}: {e}", parent.display()))?;
+  }
+  let temp_path = path.with_extension(format!("json.{}-{}.tmp", process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+  fs::write(&temp_path, format!("{}\n", snapshot.pretty(2))).map_err(|e| format!("failed to write snapshot {}: {e}", path.display()))?;
+  return fs::rename(&temp_path, path).map_err(|e| {
+    let _ = fs::remove_file(&temp_path);
+    return format!("failed to write snapshot {}: {e}", path.display());
+  });
+}
+
+//Bodies that arent json are stored as a json string
+pub fn from_body(body: &str) -> JsonValue {
+  return jzon::parse(body).unwrap_or_else(|_| body.into());
+}
+
+//Replaces the values at the redacted paths with %%%ANY%%%, so volatile fields like timestamps match whatever value they have next time
+pub fn redact(mut snapshot: JsonValue, paths: &[String]) -> JsonValue {
+  for path in paths {
+    if let Ok(segments) = json_path::parse(path) {
+      json_path::set(&mut snapshot, &segments, &JsonValue::from("%%%ANY%%%"));
+    }
+  }
+  return snapshot;
+}
+
+//Names are reduced to characters that are safe in file names on every system
+fn file_name(name: &str) -> String {
+  return name.chars().map(|x| if x.is_ascii_alphanumeric() || x == '-' || x == '_' { x } else { '_' }).collect();
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn path_is_next_to_config_file() {
+    let directory = directory("./test/sample.json");
+    assert_eq!(directory, PathBuf::from("./test/sample.snapshots"));
+    assert_eq!(path(&directory, "recipients", "retrieval of all/some recipients works"), PathBuf::from("./test/sample.snapshots/recipients/retrieval_of_all_some_recipients_works.json"));
+  }
+
+  #[test]
+  fn redact_and_write_snapshot() {
+    let snapshot = redact(from_body(r#"[{"id":1,"created_at":"2024-01-01"},{"id":2,"created_at":"2024-01-02"}]"#), &[String::from("$[*].created_at")]);
+    assert_eq!(snapshot.dump(), r#"[{"id":1,"created_at":"%%%ANY%%%"},{"id":2,"created_at":"%%%ANY%%%"}]"#);
+    assert_eq!(from_body("plain text"), JsonValue::from("plain text"));
+
+    let path = path(&std::env::temp_dir().join(format!("trest_snapshots_{}", std::process::id())), "chain", "test");
+    assert_eq!(read(&path), Ok(None));
+    write(&path, &snapshot).unwrap();
+    assert_eq!(read(&path), Ok(Some(snapshot)));
+    fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn configs_with_the_same_chain_share_snapshot() {
+    let directory = std::env::temp_dir().join(format!("trest_shared_snapshots_{}", process::id()));
+    let path = path(&directory, "chain", "test");
+    let snapshots: Vec<JsonValue> = (0..2).map(|x| jzon::parse(&format!(r#"{{"config":{x},"items":{}}}"#, JsonValue::from(vec![x; 1000]).dump())).unwrap()).collect();
+
+    //one worker per config records its snapshot over and over while the other one reads it
+    std::thread::scope(|scope| {
+      for snapshot in &snapshots {
+        let path = &path;
+        let snapshots = &snapshots;
+        scope.spawn(move || {
+          for _ in 0..50 {
+            write(path, snapshot).unwrap();
+            let read = read(path).unwrap().unwrap();
+            assert!(snapshots.contains(&read));
+          }
+        });
+      }
+    });
+
+    assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
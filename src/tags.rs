@@ -0,0 +1,194 @@
+use std::fmt;
+
+//A boolean expression over the tags of a test like "smoke and not (slow or destructive)"
+//not binds stronger than and, which binds stronger than or
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagExpression {
+  Tag(String),
+  Not(Box<TagExpression>),
+  And(Box<TagExpression>, Box<TagExpression>),
+  Or(Box<TagExpression>, Box<TagExpression>),
+}
+
+//The --tags and --exclude-tags of a run, tests that dont match are deselected instead of run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TagFilter {
+  //a test has to match any of these, if there are any
+  pub include: Vec<TagExpression>,
+  //a test must not match any of these
+  pub exclude: Vec<TagExpression>,
+}
+
+const KEYWORDS: [&str; 3] = ["and", "or", "not"];
+
+pub fn is_valid_tag(tag: &str) -> bool {
+  return !tag.is_empty() && !KEYWORDS.contains(&tag) && tag.chars().all(|x| x.is_alphanumeric() || matches!(x, '_' | '-' | ':' | '.'));
+}
+
+impl TagExpression {
+  pub fn parse(input: &str) -> Result<TagExpression, String> {
+    let tokens = tokenize(input)?;
+    let mut position = 0;
+    let expression = parse_or(&tokens, &mut position)?;
+    if position < tokens.len() {
+      return Err(format!("unexpected \"{}\" in tag expression \"{input}\"", tokens[position]));
+    }
+    return Ok(expression);
+  }
+
+  pub fn matches(&self, tags: &[String]) -> bool {
+    return match self {
+      TagExpression::Tag(tag) => tags.contains(tag),
+      TagExpression::Not(expression) => !expression.matches(tags),
+      TagExpression::And(left, right) => left.matches(tags) && right.matches(tags),
+      TagExpression::Or(left, right) => left.matches(tags) || right.matches(tags),
+    };
+  }
+}
+
+//Parentheses are only added where they are needed, so the output parses to the same expression
+impl fmt::Display for TagExpression {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    return match self {
+      TagExpression::Tag(tag) => write!(f, "{tag}"),
+      TagExpression::Not(expression) if matches!(**expression, TagExpression::Tag(_) | TagExpression::Not(_)) => write!(f, "not {expression}"),
+      TagExpression::Not(expression) => write!(f, "not ({expression})"),
+      TagExpression::And(left, right) => {
+        let wrap = |x: &TagExpression| if matches!(x, TagExpression::Or(_, _)) { format!("({x})") } else { x.to_string() };
+        write!(f, "{} and {}", wrap(left), wrap(right))
+      },
+      TagExpression::Or(left, right) => write!(f, "{left} or {right}"),
+    };
+  }
+}
+
+impl TagFilter {
+  pub fn is_empty(&self) -> bool {
+    return self.include.is_empty() && self.exclude.is_empty();
+  }
+
+  //Returns why a test with these tags doesnt run, or None if it is selected
+  pub fn deselect_reason(&self, tags: &[String]) -> Option<String> {
+    if !self.include.is_empty() && !self.include.iter().any(|x| x.matches(tags)) {
+      return Some(format!("deselected by {}", self.include.iter().map(|x| format!("--tags=\"{x}\"")).collect::<Vec<String>>().join(" ")));
+    }
+    if let Some(expression) = self.exclude.iter().find(|x| x.matches(tags)) {
+      return Some(format!("deselected by --exclude-tags=\"{expression}\""));
+    }
+    return None;
+  }
+}
+
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+  let mut tokens: Vec<String> = Vec::new();
+  let mut word = String::new();
+
+  for character in input.chars() {
+    if character.is_whitespace() || character == '(' || character == ')' {
+      if !word.is_empty() {
+        tokens.push(std::mem::take(&mut word));
+      }
+      if !character.is_whitespace() {
+        tokens.push(character.to_string());
+      }
+    } else if character.is_alphanumeric() || matches!(character, '_' | '-' | ':' | '.') {
+      word.push(character);
+    } else {
+      return Err(format!("unexpected \"{character}\" in tag expression \"{input}\""));
+    }
+  }
+  if !word.is_empty() {
+    tokens.push(word);
+  }
+
+  if tokens.is_empty() {
+    return Err(String::from("tag expression is empty"));
+  }
+  return Ok(tokens);
+}
+
+fn parse_or(tokens: &[String], position: &mut usize) -> Result<TagExpression, String> {
+  let mut left = parse_and(tokens, position)?;
+  while tokens.get(*position).is_some_and(|x| x == "or") {
+    *position += 1;
+    left = TagExpression::Or(Box::new(left), Box::new(parse_and(tokens, position)?));
+  }
+  return Ok(left);
+}
+
+fn parse_and(tokens: &[String], position: &mut usize) -> Result<TagExpression, String> {
+  let mut left = parse_not(tokens, position)?;
+  while tokens.get(*position).is_some_and(|x| x == "and") {
+    *position += 1;
+    left = TagExpression::And(Box::new(left), Box::new(parse_not(tokens, position)?));
+  }
+  return Ok(left);
+}
+
+fn parse_not(tokens: &[String], position: &mut usize) -> Result<TagExpression, String> {
+  let Some(token) = tokens.get(*position) else {
+    return Err(format!("expected a tag after \"{}\"", tokens[*position - 1]));
+  };
+  *position += 1;
+
+  return match token.as_str() {
+    "not" => Ok(TagExpression::Not(Box::new(parse_not(tokens, position)?))),
+    "(" => {
+      let expression = parse_or(tokens, position)?;
+      if !tokens.get(*position).is_some_and(|x| x == ")") {
+        return Err(String::from("missing closing ) in tag expression"));
+      }
+      *position += 1;
+      Ok(expression)
+    },
+    "and" | "or" | ")" => Err(format!("expected a tag but got \"{token}\"")),
+    tag => Ok(TagExpression::Tag(tag.to_string())),
+  };
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  fn tags(input: &[&str]) -> Vec<String> {
+    return input.iter().map(ToString::to_string).collect();
+  }
+
+  #[test]
+  fn parse_respects_precedence_and_parentheses() {
+    assert_eq!(TagExpression::parse("smoke and not slow or auth").unwrap().to_string(), "smoke and not slow or auth");
+    assert_eq!(TagExpression::parse("smoke and (slow or auth)").unwrap().to_string(), "smoke and (slow or auth)");
+    assert_eq!(TagExpression::parse("not (smoke and slow)").unwrap().to_string(), "not (smoke and slow)");
+    assert_eq!(TagExpression::parse("smoke"), Ok(TagExpression::Tag(String::from("smoke"))));
+  }
+
+  #[test]
+  fn parse_reports_errors() {
+    assert_eq!(TagExpression::parse(""), Err(String::from("tag expression is empty")));
+    assert_eq!(TagExpression::parse("smoke and"), Err(String::from("expected a tag after \"and\"")));
+    assert_eq!(TagExpression::parse("(smoke or slow"), Err(String::from("missing closing ) in tag expression")));
+    assert_eq!(TagExpression::parse("smoke slow"), Err(String::from("unexpected \"slow\" in tag expression \"smoke slow\"")));
+    assert_eq!(TagExpression::parse("smoke & slow"), Err(String::from("unexpected \"&\" in tag expression \"smoke & slow\"")));
+    assert_eq!(TagExpression::parse("or smoke"), Err(String::from("expected a tag but got \"or\"")));
+  }
+
+  #[test]
+  fn matches_tags() {
+    let expression = TagExpression::parse("smoke and not slow").unwrap();
+    assert!(expression.matches(&tags(&["smoke", "auth"])));
+    assert!(!expression.matches(&tags(&["smoke", "slow"])));
+    assert!(!expression.matches(&tags(&[])));
+  }
+
+  #[test]
+  fn tag_filter_deselects() {
+    let filter = TagFilter {
+      include: vec![TagExpression::parse("smoke").unwrap(), TagExpression::parse("auth").unwrap()],
+      exclude: vec![TagExpression::parse("destructive").unwrap()],
+    };
+    assert_eq!(filter.deselect_reason(&tags(&["auth"])), None);
+    assert_eq!(filter.deselect_reason(&tags(&["slow"])), Some(String::from("deselected by --tags=\"smoke\" --tags=\"auth\"")));
+    assert_eq!(filter.deselect_reason(&tags(&["smoke", "destructive"])), Some(String::from("deselected by --exclude-tags=\"destructive\"")));
+    assert_eq!(TagFilter::default().deselect_reason(&tags(&[])), None);
+  }
+}
@@ -1,21 +1,59 @@
-use crate::{Task, Config, http_request};
+use crate::{Task, Config, http_request, variables};
+use crate::variables::{Variables, CaptureResponse};
+use crate::output::outputln;
 
-pub fn run(config: &Config, task: &Task, task_name: &str) -> String {
+//Returns an error if the request or reading its response failed, like when it timed out
+pub fn run(config: &Config, task: &Task, task_name: &str, variables: &mut Variables) -> Result<String, String> {
+  let task = variables::resolve_task(task, variables);
   let response = http_request::send(
     config,
     &task.method,
     &task.endpoint,
     task.body.as_deref(),
     None,
-    None
+    task.headers.as_ref(),
+    None,
+    task.timeout_ms,
   );
+  let response = response?;
 
   let response_status = response.status();
-  let response_body = response.into_string().unwrap_or_default();
+  let response_headers = http_request::get_headers(&response);
+  let response_cookies = http_request::get_cookies(&response);
+  let response_body = http_request::read_body(response, http_request::request_timeout_ms(config, task.timeout_ms))?;
 
   if response_status >= 400 {
-    println!("Task \x1b[96m{task_name}\x1b[0m got an error while trying to send a web request:\n\x1b[91m{response_body}\x1b[0m");
+    outputln!("Task \x1b[96m{task_name}\x1b[0m got an error while trying to send a web request:\n\x1b[91m{response_body}\x1b[0m");
+  }
+
+  if task.capture.is_some() {
+    let capture_response = CaptureResponse {
+      status_code: response_status,
+      headers: &response_headers,
+      cookies: &response_cookies,
+      body: &jzon::parse(&response_body).unwrap_or(jzon::Null),
+    };
+    for message in variables::capture(task.capture.as_ref().unwrap(), &capture_response, variables) {
+      outputln!("Task \x1b[96m{task_name}\x1b[0m \x1b[93m{message}\x1b[0m");
+    }
   }
 
-  return response_body;
-}
\ No newline at end of file
+  return Ok(response_body);
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+  use crate::http_request::unit_test::config_for;
+  use std::net::TcpListener;
+
+  #[test]
+  fn run_reports_timeout() {
+    //the listener accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = config_for(&listener);
+    let task = Task { endpoint: String::from("/token"), method: String::from("GET"), body: None, headers: None, capture: None, timeout_ms: Some(50) };
+
+    assert_eq!(run(&config, &task, "login", &mut Variables::new()), Err(String::from("request timed out after 50ms")));
+  }
+}
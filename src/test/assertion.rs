@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use jzon::JsonValue;
+
+use crate::{json_path, Assertion};
+use super::{diff_json, describe_json, MatchMode};
+
+pub const JSON_TYPES: [&str; 7] = ["string", "number", "integer", "boolean", "array", "object", "null"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
+pub enum AssertionOperator {
+  Equals,
+  NotEquals,
+  Gt,
+  Lt,
+  Contains,
+  Matches,
+  Exists,
+  NotExists,
+  TypeIs,
+}
+
+impl AssertionOperator {
+  pub fn parse(input: &str) -> Option<AssertionOperator> {
+    return match input {
+      "equals" => Some(AssertionOperator::Equals),
+      "not_equals" => Some(AssertionOperator::NotEquals),
+      "gt" => Some(AssertionOperator::Gt),
+      "lt" => Some(AssertionOperator::Lt),
+      "contains" => Some(AssertionOperator::Contains),
+      "matches" => Some(AssertionOperator::Matches),
+      "exists" => Some(AssertionOperator::Exists),
+      "not_exists" => Some(AssertionOperator::NotExists),
+      "type_is" => Some(AssertionOperator::TypeIs),
+      _ => None,
+    };
+  }
+
+  pub fn name(self) -> &'static str {
+    return match self {
+      AssertionOperator::Equals => "equals",
+      AssertionOperator::NotEquals => "not_equals",
+      AssertionOperator::Gt => "gt",
+      AssertionOperator::Lt => "lt",
+      AssertionOperator::Contains => "contains",
+      AssertionOperator::Matches => "matches",
+      AssertionOperator::Exists => "exists",
+      AssertionOperator::NotExists => "not_exists",
+      AssertionOperator::TypeIs => "type_is",
+    };
+  }
+
+  //exists and not_exists only look at the path, every other operator compares against a value
+  pub fn needs_value(self) -> bool {
+    return !matches!(self, AssertionOperator::Exists | AssertionOperator::NotExists);
+  }
+}
+
+//Checks a single assertion against the response body and returns why it failed, or None if it passed
+pub fn check(assertion: &Assertion, body: &JsonValue, test_responses: &HashMap<String, JsonValue>) -> Option<String> {
+  let path = &assertion.path;
+  let segments = match json_path::parse(path) {
+    Ok(x) => x,
+    Err(e) => return Some(e),
+  };
+  let actual = json_path::get(body, &segments);
+  let expected = jzon::parse(assertion.value.as_deref().unwrap_or("null")).unwrap_or(JsonValue::Null);
+
+  if assertion.operator == AssertionOperator::Exists {
+    return if actual.is_some() { None } else { Some(format!("{path}: expected to exist")) };
+  }
+  if assertion.operator == AssertionOperator::NotExists {
+    return actual.map(|x| format!("{path}: expected not to exist got {}", describe_json(&x)));
+  }
+
+  let Some(actual) = actual else {
+    return Some(format!("{path}: expected {} {} but it doesnt exist", describe_operator(assertion.operator), describe_json(&expected)));
+  };
+
+  let passed = match assertion.operator {
+    AssertionOperator::Equals => diff_json(&expected, &actual, test_responses, path, MatchMode::Exact).is_empty(),
+    AssertionOperator::NotEquals => !diff_json(&expected, &actual, test_responses, path, MatchMode::Exact).is_empty(),
+    AssertionOperator::Gt => compare(&actual, &expected) == Some(std::cmp::Ordering::Greater),
+    AssertionOperator::Lt => compare(&actual, &expected) == Some(std::cmp::Ordering::Less),
+    AssertionOperator::Contains => contains(&actual, &expected, test_responses),
+    AssertionOperator::Matches => regex_lite::Regex::new(expected.as_str().unwrap_or_default()).is_ok_and(|x| x.is_match(&scalar_to_string(&actual))),
+    AssertionOperator::TypeIs => expected.as_str().is_some_and(|x| type_name(&actual) == x || (x == "number" && actual.is_number())),
+    AssertionOperator::Exists | AssertionOperator::NotExists => true,
+  };
+
+  if passed {
+    return None;
+  }
+
+  if assertion.operator == AssertionOperator::TypeIs {
+    return Some(format!("{path}: expected type {} got {}", expected.as_str().unwrap_or_default(), type_name(&actual)));
+  }
+  return Some(format!("{path}: expected {} {} got {}", describe_operator(assertion.operator), describe_json(&expected), describe_json(&actual)));
+}
+
+fn describe_operator(operator: AssertionOperator) -> &'static str {
+  return match operator {
+    AssertionOperator::Equals => "to equal",
+    AssertionOperator::NotEquals => "not to equal",
+    AssertionOperator::Gt => "to be greater than",
+    AssertionOperator::Lt => "to be less than",
+    AssertionOperator::Contains => "to contain",
+    AssertionOperator::Matches => "to match",
+    AssertionOperator::Exists => "to exist",
+    AssertionOperator::NotExists => "not to exist",
+    AssertionOperator::TypeIs => "to be of type",
+  };
+}
+
+//Numbers are compared numerically and strings lexicographically, so ISO 8601 timestamps can be compared as well
+fn compare(actual: &JsonValue, expected: &JsonValue) -> Option<std::cmp::Ordering> {
+  if actual.is_number() && expected.is_number() {
+    return actual.as_f64().unwrap_or_default().partial_cmp(&expected.as_f64().unwrap_or_default());
+  }
+  if actual.is_string() && expected.is_string() {
+    return Some(actual.as_str().unwrap_or_default().cmp(expected.as_str().unwrap_or_default()));
+  }
+  return None;
+}
+
+//Strings contain substrings, arrays contain an element matching expected and objects contain expected as a subset
+fn contains(actual: &JsonValue, expected: &JsonValue, test_responses: &HashMap<String, JsonValue>) -> bool {
+  if actual.is_string() {
+    return expected.is_string() && actual.as_str().unwrap_or_default().contains(expected.as_str().unwrap_or_default());
+  }
+  if actual.is_array() {
+    return actual.members().any(|x| diff_json(expected, x, test_responses, "$", MatchMode::Subset).is_empty());
+  }
+  if actual.is_object() {
+    return expected.is_object() && diff_json(expected, actual, test_responses, "$", MatchMode::Subset).is_empty();
+  }
+  return false;
+}
+
+fn scalar_to_string(value: &JsonValue) -> String {
+  if value.is_string() {
+    return value.as_str().unwrap_or_default().to_string();
+  }
+  return value.dump();
+}
+
+pub fn type_name(value: &JsonValue) -> &'static str {
+  if value.is_string() {
+    return "string";
+  }
+  if value.is_number() {
+    return if value.as_f64().is_some_and(|x| x.fract() == 0.0) { "integer" } else { "number" };
+  }
+  if value.is_boolean() {
+    return "boolean";
+  }
+  if value.is_array() {
+    return "array";
+  }
+  if value.is_object() {
+    return "object";
+  }
+  return "null";
+}
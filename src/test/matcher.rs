@@ -0,0 +1,127 @@
+use std::sync::LazyLock;
+use jzon::JsonValue;
+use regex_lite::Regex;
+use uuid::Uuid;
+
+use crate::util;
+
+//Matchers are strings like %%%ANY_NUMBER%%% or %%%BETWEEN(1,100)%%% in an expected body, that match values instead of being compared to them
+//Arguments are everything between the parentheses, ONE_OF splits them at commas that arent inside single quotes
+#[derive(Clone, Debug)]
+pub enum Matcher {
+  Any,
+  AnyString,
+  AnyUuid,
+  AnyNumber,
+  AnyInteger,
+  AnyBool,
+  AnyArray,
+  AnyObject,
+  NotNull,
+  Iso8601,
+  Email,
+  Regex(Regex),
+  //both ends are included
+  Between(f64, f64),
+  OneOf(Vec<String>),
+}
+
+const MATCHERS: [&str; 11] = ["ANY", "ANY_STRING", "ANY_UUID", "ANY_NUMBER", "ANY_INTEGER", "ANY_BOOL", "ANY_ARRAY", "ANY_OBJECT", "NOT_NULL", "ISO8601", "EMAIL"];
+const MATCHERS_WITH_ARGUMENTS: [&str; 3] = ["REGEX", "BETWEEN", "ONE_OF"];
+
+//a date, optionally followed by a time with seconds, fractions and a timezone
+//compiled once instead of for every compared value, the patterns are fixed so they cant fail to compile
+static ISO8601_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}(T\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?)?$").unwrap());
+static EMAIL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap());
+
+//Returns None if input isnt a matcher, references like %%%[[[test]]]...[[[key]]]%%% arent matchers either
+pub fn parse(input: &str) -> Option<Result<Matcher, String>> {
+  let inner = input.strip_prefix("%%%")?.strip_suffix("%%%")?;
+  if inner.starts_with("[[[") {
+    return None;
+  }
+
+  let (name, arguments) = match inner.split_once('(') {
+    None => (inner, None),
+    Some((name, rest)) => match rest.strip_suffix(')') {
+      Some(arguments) => (name, Some(arguments)),
+      None => return Some(Err(format!("matcher {input} is missing a closing )"))),
+    },
+  };
+
+  return Some(match (name, arguments) {
+    ("ANY", None) => Ok(Matcher::Any),
+    ("ANY_STRING", None) => Ok(Matcher::AnyString),
+    ("ANY_UUID", None) => Ok(Matcher::AnyUuid),
+    ("ANY_NUMBER", None) => Ok(Matcher::AnyNumber),
+    ("ANY_INTEGER", None) => Ok(Matcher::AnyInteger),
+    ("ANY_BOOL", None) => Ok(Matcher::AnyBool),
+    ("ANY_ARRAY", None) => Ok(Matcher::AnyArray),
+    ("ANY_OBJECT", None) => Ok(Matcher::AnyObject),
+    ("NOT_NULL", None) => Ok(Matcher::NotNull),
+    ("ISO8601", None) => Ok(Matcher::Iso8601),
+    ("EMAIL", None) => Ok(Matcher::Email),
+    ("REGEX", Some(pattern)) => Regex::new(pattern).map(Matcher::Regex).map_err(|e| format!("matcher {input} has an invalid regular expression: {e}")),
+    ("BETWEEN", Some(arguments)) => parse_between(arguments).ok_or(format!("matcher {input} expects a minimum and a maximum number like BETWEEN(1,100)")),
+    ("ONE_OF", Some(arguments)) if !arguments.trim().is_empty() => parse_one_of(arguments).ok_or(format!("matcher {input} has an unclosed quote or text after a quoted value, values with commas are quoted like ONE_OF('Doe, John',other)")),
+    ("ONE_OF", Some(_)) => Err(format!("matcher {input} expects at least one value like ONE_OF(a,b)")),
+    (name, Some(_)) if MATCHERS.contains(&name) => Err(format!("matcher {name} doesnt take arguments")),
+    (name, None) if MATCHERS_WITH_ARGUMENTS.contains(&name) => Err(format!("matcher {name} needs arguments like {name}(...)")),
+    (name, _) => Err(match MATCHERS.iter().chain(MATCHERS_WITH_ARGUMENTS.iter()).find(|x| util::edit_distance(x, name) <= 2) {
+      Some(suggestion) => format!("unknown matcher {name}, did you mean {suggestion}?"),
+      None => format!("unknown matcher {name}, expected one of {}", MATCHERS.iter().chain(MATCHERS_WITH_ARGUMENTS.iter()).copied().collect::<Vec<&str>>().join(", ")),
+    }),
+  });
+}
+
+fn parse_between(arguments: &str) -> Option<Matcher> {
+  let (min, max) = arguments.split_once(',')?;
+  let (min, max) = (min.trim().parse::<f64>().ok()?, max.trim().parse::<f64>().ok()?);
+  return if min <= max { Some(Matcher::Between(min, max)) } else { None };
+}
+
+//Quoted values keep everything between the quotes, including commas and whitespace
+fn parse_one_of(arguments: &str) -> Option<Matcher> {
+  let mut values: Vec<String> = Vec::new();
+  let mut rest = arguments;
+  loop {
+    let value = rest.trim_start();
+    let after = if let Some(quoted) = value.strip_prefix('\'') {
+      let (quoted, after) = quoted.split_once('\'')?;
+      values.push(quoted.to_string());
+      after.trim_start()
+    } else {
+      let end = value.find(',').unwrap_or(value.len());
+      values.push(value[..end].trim_end().to_string());
+      &value[end..]
+    };
+    match after.strip_prefix(',') {
+      Some(x) => rest = x,
+      None if after.is_empty() => return Some(Matcher::OneOf(values)),
+      None => return None,
+    }
+  }
+}
+
+impl Matcher {
+  pub fn matches(&self, actual: &JsonValue) -> bool {
+    let actual_str = actual.as_str();
+    return match self {
+      Matcher::Any => true,
+      Matcher::AnyString => actual.is_string(),
+      Matcher::AnyUuid => actual_str.is_some_and(|x| Uuid::parse_str(x).is_ok()),
+      Matcher::AnyNumber => actual.is_number(),
+      Matcher::AnyInteger => actual.as_f64().is_some_and(|x| x.fract() == 0.0),
+      Matcher::AnyBool => actual.is_boolean(),
+      Matcher::AnyArray => actual.is_array(),
+      Matcher::AnyObject => actual.is_object(),
+      Matcher::NotNull => !actual.is_null(),
+      Matcher::Iso8601 => actual_str.is_some_and(|x| ISO8601_REGEX.is_match(x)),
+      Matcher::Email => actual_str.is_some_and(|x| EMAIL_REGEX.is_match(x)),
+      Matcher::Regex(regex) => actual_str.is_some_and(|x| regex.is_match(x)),
+      Matcher::Between(min, max) => actual.as_f64().is_some_and(|x| x >= *min && x <= *max),
+      //strings are compared by their content, numbers and booleans by how they are written
+      Matcher::OneOf(values) => values.iter().any(|x| if actual.is_string() { actual_str == Some(x.as_str()) } else { (actual.is_number() || actual.is_boolean()) && actual.dump() == *x }),
+    };
+  }
+}
@@ -1,84 +1,272 @@
 #[cfg(test)]
 mod tests;
+pub mod assertion;
+pub mod matcher;
+pub mod schema;
 
 use std::collections::HashMap;
+use std::time::Duration;
 use jzon::JsonValue;
-use uuid::Uuid;
+use linked_hash_map::LinkedHashMap;
 
-use crate::{task, http_request, Test, Config, ConfigFile, TestOutcome};
+use crate::{task, http_request, json_path, snapshot, Test, Config, ConfigFile, TestOutcome, Assertion};
+use crate::variables::Variables;
 
 #[derive(PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum TestResults {
   Passed, 
-  Failed(TestOutcome),
+  Failed(TestFailure),
 }
 
-#[allow(clippy::if_same_then_else)]
-pub fn check_test_result(test: &Test, response_status_code: u16, response_content_type: &str, response_body: &str, test_responses: HashMap<String, jzon::JsonValue>) -> TestResults {
+//The actual outcome contains the actual values of everything that didnt match the expected outcome
+//body_diff and body_contains_diff list every difference between the expected and actual json body
+//failed_assertions has one message for every assertion in actual_outcome.assertions
+//snapshot_diff lists every difference between the recorded snapshot and the actual body
+//schema_errors lists every part of the actual body that didnt match the schema
+//error is set instead of all of those if there was no response to check, like when the request timed out or the test panicked
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct TestFailure {
+  pub actual_outcome: TestOutcome,
+  pub body_diff: Vec<String>,
+  pub body_contains_diff: Vec<String>,
+  pub failed_assertions: Vec<String>,
+  pub snapshot_diff: Vec<String>,
+  pub schema_errors: Vec<String>,
+  pub response_body: String,
+  pub error: Option<String>,
+}
+
+//Exact requires objects to have the same keys and arrays the same elements
+//Subset only requires the keys of expected objects and the elements of expected arrays to be present in actual
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchMode {
+  Exact,
+  Subset,
+}
+
+//snapshot is the redacted snapshot of the test, None if there is none to compare against yet
+#[allow(clippy::if_same_then_else, clippy::too_many_arguments)]
+pub fn check_test_result(test: &Test, response_status_code: u16, response_content_type: &str, response_headers: &LinkedHashMap<String, String>, response_body: &str, response_duration: Duration, snapshot: Option<&JsonValue>, test_responses: &HashMap<String, jzon::JsonValue>) -> TestResults {
   let mut actual_outcome: TestOutcome = TestOutcome::default();
+  let mut body_diff: Vec<String> = Vec::new();
 
   if test.expected_outcome.body_equals.is_some() {
-    if response_content_type == "application/json" && !expected_equals_actual_json(jzon::parse(test.expected_outcome.body_equals.as_ref().unwrap()).unwrap_or(JsonValue::new_object()), jzon::parse(response_body).unwrap_or(JsonValue::new_object()), test_responses) {
-      actual_outcome.body_equals = Some(String::from(response_body));
+    if response_content_type == "application/json" {
+      body_diff = diff_json(&jzon::parse(test.expected_outcome.body_equals.as_ref().unwrap()).unwrap_or(JsonValue::new_object()), &jzon::parse(response_body).unwrap_or(JsonValue::new_object()), test_responses, "$", MatchMode::Exact);
+      if !body_diff.is_empty() {
+        actual_outcome.body_equals = Some(String::from(response_body));
+      }
     } else if response_content_type != "application/json" && test.expected_outcome.body_equals.as_ref().unwrap() != response_body {
       actual_outcome.body_equals = Some(String::from(response_body));
     }
   }
 
+  let mut schema_errors: Vec<String> = Vec::new();
+
+  if test.expected_outcome.body_matches_schema.is_some() {
+    let expected_schema = jzon::parse(test.expected_outcome.body_matches_schema.as_ref().unwrap()).unwrap_or(JsonValue::new_object());
+    schema_errors = match jzon::parse(response_body) {
+      Ok(body) => schema::validate(&expected_schema, &body),
+      Err(_) => vec![String::from("$: response body isnt json")],
+    };
+    if !schema_errors.is_empty() {
+      actual_outcome.body_matches_schema = Some(String::from(response_body));
+    }
+  }
+
+  let mut body_contains_diff: Vec<String> = Vec::new();
+
+  if test.expected_outcome.body_contains.is_some() {
+    if response_content_type == "application/json" {
+      body_contains_diff = diff_json(&jzon::parse(test.expected_outcome.body_contains.as_ref().unwrap()).unwrap_or(JsonValue::new_object()), &jzon::parse(response_body).unwrap_or(JsonValue::new_object()), test_responses, "$", MatchMode::Subset);
+      if !body_contains_diff.is_empty() {
+        actual_outcome.body_contains = Some(String::from(response_body));
+      }
+    } else if !response_body.contains(test.expected_outcome.body_contains.as_ref().unwrap().as_str()) {
+      actual_outcome.body_contains = Some(String::from(response_body));
+    }
+  }
+
   if test.expected_outcome.status_code_equals.is_some()
   && response_status_code as usize != test.expected_outcome.status_code_equals.unwrap() {
     actual_outcome.status_code_equals = Some(response_status_code as usize);
   }
 
-  if actual_outcome.body_equals.is_some() || actual_outcome.status_code_equals.is_some() {
-    println!("\x1b[91mfailed\x1b[0m");
-    return TestResults::Failed(actual_outcome);
+  if test.expected_outcome.headers_equal.is_some()
+  && test.expected_outcome.headers_equal.as_ref().unwrap().iter().any(|(name, value)| response_headers.get(&name.to_lowercase()) != Some(value)) {
+    actual_outcome.headers_equal = Some(get_actual_headers(test.expected_outcome.headers_equal.as_ref().unwrap().keys(), response_headers));
+  }
+
+  if test.expected_outcome.headers_contain.is_some()
+  && test.expected_outcome.headers_contain.as_ref().unwrap().iter().any(|(name, value)| !response_headers.get(&name.to_lowercase()).is_some_and(|x| x.contains(value.as_str()))) {
+    actual_outcome.headers_contain = Some(get_actual_headers(test.expected_outcome.headers_contain.as_ref().unwrap().keys(), response_headers));
+  }
+
+  if test.expected_outcome.headers_absent.is_some()
+  && test.expected_outcome.headers_absent.as_ref().unwrap().iter().any(|name| response_headers.contains_key(&name.to_lowercase())) {
+    actual_outcome.headers_absent = Some(test.expected_outcome.headers_absent.as_ref().unwrap().iter().filter(|name| response_headers.contains_key(&name.to_lowercase())).cloned().collect());
+  }
+
+  let response_duration_ms = u64::try_from(response_duration.as_millis()).unwrap_or(u64::MAX);
+  if test.expected_outcome.max_duration_ms.is_some() && response_duration_ms > test.expected_outcome.max_duration_ms.unwrap() {
+    actual_outcome.max_duration_ms = Some(response_duration_ms);
+  }
+
+  let mut snapshot_diff: Vec<String> = Vec::new();
+
+  if test.expected_outcome.body_matches_snapshot.is_some() && snapshot.is_some() {
+    snapshot_diff = diff_json(snapshot.unwrap(), &snapshot::from_body(response_body), test_responses, "$", MatchMode::Exact);
+    if !snapshot_diff.is_empty() {
+      actual_outcome.body_matches_snapshot.clone_from(&test.expected_outcome.body_matches_snapshot);
+    }
+  }
+
+  let mut failed_assertions: Vec<String> = Vec::new();
+
+  if test.expected_outcome.assertions.is_some() {
+    //the body of this response, test_responses can still hold the one of an earlier attempt
+    let body = jzon::parse(response_body).ok();
+    let failed: Vec<(&Assertion, String)> = test.expected_outcome.assertions.as_ref().unwrap()
+      .iter()
+      .filter_map(|x| if body.is_some() { assertion::check(x, body.as_ref().unwrap(), test_responses).map(|message| (x, message)) } else { Some((x, format!("{}: response body isnt json", x.path))) })
+      .collect();
+    if !failed.is_empty() {
+      actual_outcome.assertions = Some(failed.iter().map(|(x, _)| (*x).clone()).collect());
+      failed_assertions = failed.into_iter().map(|(_, message)| message).collect();
+    }
+  }
+
+  if actual_outcome != TestOutcome::default() {
+    return TestResults::Failed(TestFailure { actual_outcome, body_diff, body_contains_diff, failed_assertions, snapshot_diff, schema_errors, response_body: String::from(response_body), error: None });
   }
   
-  println!("\x1b[92mpassed\x1b[0m");
   return TestResults::Passed;
 }
 
-pub fn stringify_test_outcome(actual_outcome: &TestOutcome, expected_outcome: &TestOutcome, response_body: &str, test_chain_name: &str, test_name: &str) -> String {
+//Drops the mismatch against a stale snapshot from a result, used once the snapshot got rewritten from the same response
+pub fn without_snapshot_mismatch(result: TestResults) -> TestResults {
+  let TestResults::Failed(mut failure) = result else {
+    return result;
+  };
+  failure.snapshot_diff.clear();
+  failure.actual_outcome.body_matches_snapshot = None;
+  if failure.actual_outcome == TestOutcome::default() && failure.error.is_none() {
+    return TestResults::Passed;
+  }
+  return TestResults::Failed(failure);
+}
+
+pub fn stringify_test_outcome(failure: &TestFailure, expected_outcome: &TestOutcome, test_chain_name: &str, test_name: &str) -> String {
+  if failure.actual_outcome == TestOutcome::default() && failure.error.is_none() {
+    return String::new();
+  }
+  return format!("Test \x1b[96m{test_chain_name}\x1b[0m: \x1b[96m{test_name}\x1b[0m \x1b[91mfailed\x1b[0m:\n{}", describe_failure(failure, expected_outcome, true));
+}
+
+//Lists everything that didnt match the expected outcome, colored for the terminal or plain for report files
+pub fn describe_failure(failure: &TestFailure, expected_outcome: &TestOutcome, colored: bool) -> String {
   let mut output_parts: Vec<String> = Vec::new();
-  
-  if actual_outcome.body_equals.is_some() || actual_outcome.status_code_equals.is_some() {
-    output_parts.push(format!("Test \x1b[96m{test_chain_name}\x1b[0m: \x1b[96m{test_name}\x1b[0m \x1b[91mfailed\x1b[0m:\n"));
+  let actual_outcome = &failure.actual_outcome;
+  let response_body = &failure.response_body;
+  let paint = |color: &str, text: String| if colored { format!("\x1b[{color}m{text}\x1b[0m") } else { text };
+
+  if failure.error.is_some() {
+    output_parts.push(paint("91", format!("{}\n", failure.error.as_ref().unwrap())));
   }
 
   if actual_outcome.body_equals.is_some() {
-    if jzon::parse(actual_outcome.body_equals.as_ref().unwrap()).is_ok() && jzon::parse(expected_outcome.body_equals.as_ref().unwrap()).is_ok() {
-      output_parts.push(format!("\x1b[91mresponse body of\n{}\ndidnt match expected outcome\n{}\n\x1b[0m", jzon::parse(actual_outcome.body_equals.as_ref().unwrap()).unwrap(), jzon::parse(expected_outcome.body_equals.as_ref().unwrap()).unwrap()));
+    if !failure.body_diff.is_empty() {
+      output_parts.push(paint("91", format!("response body didnt match expected outcome:\n{}", failure.body_diff.iter().map(|x| format!("  {x}\n")).collect::<Vec<String>>().concat())));
+    } else if jzon::parse(actual_outcome.body_equals.as_ref().unwrap()).is_ok() && jzon::parse(expected_outcome.body_equals.as_ref().unwrap()).is_ok() {
+      output_parts.push(paint("91", format!("response body of\n{}\ndidnt match expected outcome\n{}\n", jzon::parse(actual_outcome.body_equals.as_ref().unwrap()).unwrap(), jzon::parse(expected_outcome.body_equals.as_ref().unwrap()).unwrap())));
+    } else {
+      output_parts.push(paint("91", format!("response body of\n{}\ndidnt match expected outcome\n{}\n", actual_outcome.body_equals.as_ref().unwrap(), expected_outcome.body_equals.clone().unwrap_or_default())));
+    }
+  }
+
+  if actual_outcome.body_matches_schema.is_some() {
+    output_parts.push(paint("91", format!("response body didnt match schema:\n{}", failure.schema_errors.iter().map(|x| format!("  {x}\n")).collect::<Vec<String>>().concat())));
+  }
+
+  if actual_outcome.body_contains.is_some() {
+    if failure.body_contains_diff.is_empty() {
+      output_parts.push(paint("91", format!("response body of\n{}\ndidnt contain expected outcome\n{}\n", actual_outcome.body_contains.as_ref().unwrap(), expected_outcome.body_contains.clone().unwrap_or_default())));
     } else {
-      output_parts.push(format!("\x1b[91mresponse body of\n{}\ndidnt match expected outcome\n{}\n\x1b[0m", actual_outcome.body_equals.as_ref().unwrap(), expected_outcome.body_equals.clone().unwrap_or_default()));
+      output_parts.push(paint("91", format!("response body didnt contain expected outcome:\n{}", failure.body_contains_diff.iter().map(|x| format!("  {x}\n")).collect::<Vec<String>>().concat())));
     }
   }
 
   if actual_outcome.status_code_equals.is_some() {
-    output_parts.push(format!("\x1b[91mresponse status code of {} didnt match expected outcome {}\n\x1b[0m", actual_outcome.status_code_equals.unwrap(), expected_outcome.status_code_equals.unwrap()));
+    output_parts.push(paint("91", format!("response status code of {} didnt match expected outcome {}\n", actual_outcome.status_code_equals.unwrap(), expected_outcome.status_code_equals.unwrap())));
     
-    if actual_outcome.body_equals.is_none() {
-      output_parts.push(format!("\x1b[95mresponse body was {response_body}\n\x1b[0m"));
+    if actual_outcome.body_equals.is_none() && actual_outcome.body_contains.is_none() && actual_outcome.body_matches_snapshot.is_none() && actual_outcome.body_matches_schema.is_none() {
+      output_parts.push(paint("95", format!("response body was {response_body}\n")));
     }
   }
 
+  if actual_outcome.headers_equal.is_some() {
+    for (name, expected_value) in expected_outcome.headers_equal.as_ref().unwrap_or(&LinkedHashMap::new()) {
+      match actual_outcome.headers_equal.as_ref().unwrap().get(name) {
+        None => output_parts.push(paint("91", format!("response header {name} was missing, expected {expected_value}\n"))),
+        Some(actual_value) if actual_value != expected_value => output_parts.push(paint("91", format!("response header {name} of {actual_value} didnt match expected outcome {expected_value}\n"))),
+        Some(_) => (),
+      }
+    }
+  }
+
+  if actual_outcome.headers_contain.is_some() {
+    for (name, expected_value) in expected_outcome.headers_contain.as_ref().unwrap_or(&LinkedHashMap::new()) {
+      match actual_outcome.headers_contain.as_ref().unwrap().get(name) {
+        None => output_parts.push(paint("91", format!("response header {name} was missing, expected it to contain {expected_value}\n"))),
+        Some(actual_value) if !actual_value.contains(expected_value.as_str()) => output_parts.push(paint("91", format!("response header {name} of {actual_value} didnt contain expected outcome {expected_value}\n"))),
+        Some(_) => (),
+      }
+    }
+  }
+
+  if actual_outcome.headers_absent.is_some() {
+    for name in actual_outcome.headers_absent.as_ref().unwrap() {
+      output_parts.push(paint("91", format!("response header {name} was present, expected it to be absent\n")));
+    }
+  }
+
+  if actual_outcome.max_duration_ms.is_some() {
+    output_parts.push(paint("91", format!("response took {}ms, expected at most {}ms\n", actual_outcome.max_duration_ms.unwrap(), expected_outcome.max_duration_ms.unwrap_or_default())));
+  }
+
+  if actual_outcome.body_matches_snapshot.is_some() {
+    output_parts.push(paint("91", format!("response body didnt match snapshot, rerun with --update-snapshots if the change is intended:\n{}", failure.snapshot_diff.iter().map(|x| format!("  {x}\n")).collect::<Vec<String>>().concat())));
+  }
+
+  if actual_outcome.assertions.is_some() {
+    output_parts.push(paint("91", format!("assertions on the response body failed:\n{}", failure.failed_assertions.iter().map(|x| format!("  {x}\n")).collect::<Vec<String>>().concat())));
+  }
+
   return output_parts.concat()
 }
 
-pub fn run_test_http_request(mut test: Test, config: &Config, config_file: &ConfigFile, test_responses: HashMap<String, jzon::JsonValue>) -> ureq::Response {
-  let before_task_results: HashMap<String, String> = run_test_before_tasks(test.clone(), config, config_file);
+//Picks the given headers out of the response, using the casing of the expected outcome so they can be looked up again when stringifying
+//Headers missing in the response are left out
+fn get_actual_headers<'a>(names: impl Iterator<Item = &'a String>, response_headers: &LinkedHashMap<String, String>) -> LinkedHashMap<String, String> {
+  return names
+    .filter_map(|name| response_headers.get(&name.to_lowercase()).map(|value| (name.clone(), value.clone())))
+    .collect();
+}
 
+//The before tasks are run separately with run_test_before_tasks, so variables they capture can be substituted into test first
+pub fn run_test_http_request(mut test: Test, config: &Config, before_task_results: &HashMap<String, String>, test_responses: &HashMap<String, jzon::JsonValue>) -> Result<ureq::Response, String> {
   while test.endpoint.contains("%%%[[[") {
     let key: &str = test.endpoint.split("%%%[[[").collect::<Vec<&str>>()[1].split("]]]...[[[").collect::<Vec<&str>>()[0];
     let index: &str = test.endpoint.split("%%%[[[").collect::<Vec<&str>>()[1].split("]]]...[[[").collect::<Vec<&str>>()[1].split("]]]%%%").collect::<Vec<&str>>()[0];
 
-    let mut value = test_responses.get(&key.to_string()).unwrap_or(&jzon::Null);
+    let mut value = test_responses.get(key).unwrap_or(&jzon::Null);
 
     let empty_vec: Vec<JsonValue> = Vec::new();
     if value.is_array() {
       value = value.as_array().unwrap_or(&empty_vec).first().unwrap_or(&JsonValue::Null);
     }
-    test.endpoint = test.endpoint.replace(format!("%%%[[[{key}]]]...[[[{index}]]]%%%").as_str(), value.get(&index).unwrap_or(&jzon::Null).to_string().as_str());
+    test.endpoint = test.endpoint.replace(format!("%%%[[[{key}]]]...[[[{index}]]]%%%").as_str(), value.get(index).unwrap_or(&jzon::Null).to_string().as_str());
   }
 
   while test.body.is_some() && test.body.clone().unwrap().contains("%%%[[[") {
@@ -86,13 +274,13 @@ pub fn run_test_http_request(mut test: Test, config: &Config, config_file: &Conf
     let key: &str = body.split("%%%[[[").collect::<Vec<&str>>()[1].split("]]]...[[[").collect::<Vec<&str>>()[0];
     let index: &str = body.split("%%%[[[").collect::<Vec<&str>>()[1].split("]]]...[[[").collect::<Vec<&str>>()[1].split("]]]%%%").collect::<Vec<&str>>()[0];
 
-    let mut value = test_responses.get(&key.to_string()).unwrap_or(&jzon::Null);
+    let mut value = test_responses.get(key).unwrap_or(&jzon::Null);
 
     let empty_vec: Vec<JsonValue> = Vec::new();
     if value.is_array() {
       value = value.as_array().unwrap_or(&empty_vec).first().unwrap_or(&JsonValue::Null);
     }
-    test.body = Some(test.body.unwrap().replace(format!("%%%[[[{key}]]]...[[[{index}]]]%%%").as_str(), value.get(&index).unwrap_or(&jzon::Null).to_string().as_str()));
+    test.body = Some(test.body.unwrap().replace(format!("%%%[[[{key}]]]...[[[{index}]]]%%%").as_str(), value.get(index).unwrap_or(&jzon::Null).to_string().as_str()));
   }
 
   return http_request::send(
@@ -101,108 +289,138 @@ pub fn run_test_http_request(mut test: Test, config: &Config, config_file: &Conf
     test.endpoint.as_str(),
     test.body.as_deref(),
     test.cookies.as_ref(),
-    Some(&before_task_results),
+    test.headers.as_ref(),
+    Some(before_task_results),
+    test.timeout_ms,
   );
 }
 
-fn run_test_before_tasks(test: Test, config: &Config, config_file: &ConfigFile) -> HashMap<String, String> {
+//Stops at the first task that failed, the test cant be sent without its result
+pub fn run_test_before_tasks(test: &Test, config: &Config, config_file: &ConfigFile, variables: &mut Variables) -> Result<HashMap<String, String>, String> {
   if test.before.is_none() {
-    return HashMap::new();
+    return Ok(HashMap::new());
   }
 
   return test.before.as_ref().unwrap()
     .iter()
     .map(|x| {
-      let res = task::run(config, config_file.tasks.get(x).expect(format!("test {x} not found").as_str()), x);
-      (x.clone(), res)
+      //validation rejects undefined tasks, this only guards against configs that skipped it
+      let task = config_file.tasks.get(x).ok_or_else(|| format!("task {x} not found"))?;
+      let res = task::run(config, task, x, variables).map_err(|e| format!("task {x}: {e}"))?;
+      return Ok((x.clone(), res));
     })
     .collect();
 }
 
-fn expected_equals_actual_json(expected: JsonValue, actual: JsonValue, test_responses: HashMap<String, jzon::JsonValue>) -> bool {
-  if expected.is_string() && expected.as_str().unwrap_or_default() == "%%%ANY%%%" {
-    return true;
+//Compares expected with actual and returns one line for every difference, starting with the path where it was found
+//An empty result means that actual matches expected
+fn diff_json(expected: &JsonValue, actual: &JsonValue, test_responses: &HashMap<String, jzon::JsonValue>, path: &str, mode: MatchMode) -> Vec<String> {
+  let expected_str = expected.as_str().unwrap_or_default();
+
+  //invalid matchers are rejected by the validation, if they get here anyway they never match
+  match matcher::parse(expected_str) {
+    Some(Ok(matcher)) if matcher.matches(actual) => return Vec::new(),
+    Some(Ok(_)) => return vec![format!("{path}: expected {} got {}", describe_json(expected), describe_json(actual))],
+    Some(Err(e)) => return vec![format!("{path}: {e}")],
+    None => (),
   }
 
-  if expected.is_string() && expected.as_str().unwrap_or_default() == "%%%ANY_STRING%%%" && actual.is_string() {
-    return true;
+  if expected_str.starts_with("%%%[[[") {
+    let referenced_value = get_referenced_value(expected_str, test_responses);
+    if referenced_value == *actual {
+      return Vec::new();
+    }
+    return vec![format!("{path}: expected {} from {expected_str} got {}", describe_json(&referenced_value), describe_json(actual))];
   }
 
-  if expected.is_string() && expected.as_str().unwrap_or_default() == "%%%ANY_UUID%%%" && actual.is_string() && Uuid::parse_str(actual.to_string().as_str()).is_ok() {
-    return true;
+  if expected.is_array() && actual.is_array() && mode == MatchMode::Subset {
+    return expected
+      .members()
+      .enumerate()
+      .filter(|(_, expected_member)| !actual.members().any(|actual_member| diff_json(expected_member, actual_member, test_responses, path, mode).is_empty()))
+      .map(|(i, expected_member)| format!("{}: no element of the actual array matched {}", json_path::index_path(path, i), describe_json(expected_member)))
+      .collect();
   }
 
-  if expected.as_str().unwrap_or_default().starts_with("%%%[[[") {
-    let start = expected.as_str().unwrap_or_default().replace("%%%", "").replace("[[[", "").replace("]]]", "");
-    let key = start.split("...").collect::<Vec<&str>>()[0].to_string();
-    let index = start.split("...").collect::<Vec<&str>>()[1].to_string();
-    let mut value = test_responses.get(&key).unwrap_or(&jzon::Null);
-    let empty_vec: Vec<JsonValue> = Vec::new();
-    if value.is_array() {
-      value = value.as_array().unwrap_or(&empty_vec).first().unwrap_or(&JsonValue::Null);
-    }
-
-    if *value.get(&index).unwrap_or(&jzon::Null) == actual {
-      return true;
+  if expected.is_array() && actual.is_array() {
+    if expected.len() != actual.len() {
+      return vec![format!("{path}: expected {} got {}", describe_json_array(expected), describe_json_array(actual))];
     }
-  }
 
-  if !expected.is_array() && !expected.is_object() {
-    return expected == actual;
+    let mut expected_members: Vec<JsonValue> = expected
+      .members()
+      .map(|x| if x.as_str().unwrap_or_default().starts_with("%%%[[[") { get_referenced_value(x.as_str().unwrap_or_default(), test_responses) } else { x.clone() })
+      .collect();
+    expected_members.sort_by(|a, b| a.as_str().unwrap_or_default().cmp(b.as_str().unwrap_or_default()));
+
+    let mut actual_members: Vec<JsonValue> = actual.members().cloned().collect();
+    actual_members.sort_by(|a, b| a.as_str().unwrap_or_default().cmp(b.as_str().unwrap_or_default()));
+
+    return expected_members
+      .iter()
+      .zip(actual_members.iter())
+      .enumerate()
+      .flat_map(|(i, (expected_member, actual_member))| diff_json(expected_member, actual_member, test_responses, &json_path::index_path(path, i), mode))
+      .collect();
   }
 
-  let default_vec: Vec<JsonValue> = Vec::new();
-  let default_json: JsonValue = JsonValue::Null;
-
-  if expected.is_array() {
-    if expected.as_array().unwrap_or(&default_vec).len() != actual.as_array().unwrap_or(&default_vec).len() {
-      return false;
-    }
+  if expected.is_object() && actual.is_object() {
+    let mut output: Vec<String> = Vec::new();
 
-    let mut expected_clone = expected.clone();
-    let mut mut_default_vec: Vec<JsonValue> = Vec::new();
-    for item in expected_clone.as_array_mut().unwrap_or(&mut mut_default_vec).into_iter() {
-      if item.is_string() && item.as_str().unwrap_or_default().starts_with("%%%[[[") {
-        let start = item.as_str().unwrap_or_default().replace("%%%", "").replace("[[[", "").replace("]]]", "");
-        let key = start.split("...").collect::<Vec<&str>>()[0].to_string();
-        let index = start.split("...").collect::<Vec<&str>>()[1].to_string();
-        let mut value = test_responses.get(&key).unwrap_or(&jzon::Null);
-        let empty_vec: Vec<JsonValue> = Vec::new();
-        if value.is_array() {
-          value = value.as_array().unwrap_or(&empty_vec).first().unwrap_or(&JsonValue::Null);
-        }
-        
-        *item = value.get(&index).unwrap_or(&jzon::Null).clone();
+    for (expected_key, expected_value) in expected.entries() {
+      if actual.has_key(expected_key) {
+        output.append(&mut diff_json(expected_value, &actual[expected_key], test_responses, &json_path::key_path(path, expected_key), mode));
+      } else {
+        output.push(format!("{}: missing key", json_path::key_path(path, expected_key)));
       }
     }
-    expected_clone.as_array_mut().unwrap().sort_by(|a, b| a.as_str().unwrap_or_default().partial_cmp(b.as_str().unwrap_or_default()).unwrap());
 
-
-    let mut actual_clone = actual.clone();
-    actual_clone.as_array_mut().unwrap().sort_by(|a, b| a.as_str().unwrap_or_default().partial_cmp(b.as_str().unwrap_or_default()).unwrap());
-
-    for i in 0..expected_clone.as_array().unwrap_or(&default_vec).len() {
-      if !expected_equals_actual_json(expected_clone.as_array().unwrap_or(&default_vec).get(i).unwrap_or(&default_json).clone(), actual_clone.as_array().unwrap_or(&default_vec).get(i).unwrap_or(&default_json).clone(), test_responses.clone()) {
-        return false;
-      }
+    for (actual_key, _) in actual.entries().filter(|(x, _)| mode == MatchMode::Exact && !expected.has_key(x)) {
+      output.push(format!("{}: unexpected key", json_path::key_path(path, actual_key)));
     }
+
+    return output;
   }
 
-  let default_object: jzon::object::Object = jzon::object::Object::new();
+  if expected == actual {
+    return Vec::new();
+  }
 
-  if expected.is_object() {
-    if expected.as_object().unwrap_or(&default_object).len() != actual.as_object().unwrap_or(&default_object).len() {
-      return false;
-    }
+  return vec![format!("{path}: expected {} got {}", describe_json(expected), describe_json(actual))];
+}
 
-    for (expected_key, expected_value) in expected.as_object().unwrap_or(&default_object).clone().into_iter() {
-      let actual_value = actual.as_object().unwrap_or(&default_object).get(&expected_key).unwrap_or(&default_json).clone();
+//Resolves references like %%%[[[test name]]]...[[[key]]]%%% to the value of key in the response of test name
+//If that response is an array, its first element is used
+fn get_referenced_value(reference: &str, test_responses: &HashMap<String, jzon::JsonValue>) -> JsonValue {
+  let start = reference.replace("%%%", "").replace("[[[", "").replace("]]]", "");
+  let key = start.split("...").next().unwrap_or_default();
+  let index = start.split("...").nth(1).unwrap_or_default();
+  let mut value = test_responses.get(key).unwrap_or(&jzon::Null);
+  if value.is_array() {
+    value = value.members().next().unwrap_or(&JsonValue::Null);
+  }
+  return value.get(index).unwrap_or(&jzon::Null).clone();
+}
 
-      if !expected_equals_actual_json(expected_value, actual_value, test_responses.clone()) {
-        return false;
-      }
-    }
+//Short json representation of a value for diffs, containers that would be too long are only described by their type
+fn describe_json(value: &JsonValue) -> String {
+  let dumped = value.dump();
+  if dumped.chars().count() <= 80 {
+    return dumped;
   }
+  if value.is_array() {
+    return describe_json_array(value);
+  }
+  if value.is_object() {
+    return format!("an object with {} keys", value.len());
+  }
+  return format!("{}...", dumped.chars().take(77).collect::<String>());
+}
 
-  return true;
-}
\ No newline at end of file
+fn describe_json_array(value: &JsonValue) -> String {
+  let dumped = value.dump();
+  if dumped.chars().count() <= 80 {
+    return dumped;
+  }
+  return format!("an array with {} elements", value.len());
+}
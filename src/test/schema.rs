@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::Path;
+use jzon::JsonValue;
+use regex_lite::Regex;
+
+use crate::json_path::{key_path, index_path};
+use super::assertion::type_name;
+use super::describe_json;
+
+//The subset of JSON Schema draft 2020-12 that body_matches_schema supports, everything else is rejected by the validation
+pub const KEYWORDS: [&str; 26] = [
+  "$schema", "$id", "$comment", "$defs", "definitions", "$ref", "title", "description", "default", "examples",
+  "type", "properties", "required", "items", "additionalProperties", "enum", "pattern",
+  "minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum", "minLength", "maxLength", "minItems", "maxItems", "const",
+];
+
+//a $ref that points back to itself would otherwise recurse forever
+const MAX_DEPTH: usize = 100;
+
+//Checks a response body against a schema and returns one line for every violation, starting with the path in the body where it was found
+//An empty result means that the body matches the schema
+pub fn validate(schema: &JsonValue, instance: &JsonValue) -> Vec<String> {
+  return validate_at(schema, schema, instance, "$", 0);
+}
+
+fn validate_at(root: &JsonValue, schema: &JsonValue, instance: &JsonValue, path: &str, depth: usize) -> Vec<String> {
+  if schema.as_bool() == Some(false) {
+    return vec![format!("{path}: no value is allowed here, got {}", describe_json(instance))];
+  }
+  if !schema.is_object() {
+    return Vec::new();
+  }
+  if depth > MAX_DEPTH {
+    return vec![format!("{path}: schema is nested more than {MAX_DEPTH} levels deep, is there a $ref that points to itself?")];
+  }
+
+  let mut output: Vec<String> = Vec::new();
+
+  if schema["$ref"].is_string() {
+    let reference = schema["$ref"].as_str().unwrap_or_default();
+    match resolve_ref(root, reference) {
+      Some(referenced) => output.append(&mut validate_at(root, referenced, instance, path, depth + 1)),
+      None => output.push(format!("{path}: $ref {reference} doesnt point to anything in the schema")),
+    }
+  }
+
+  //the other keywords only apply to some types, so a wrong type is reported on its own
+  let types: Vec<&str> = if schema["type"].is_string() { vec![schema["type"].as_str().unwrap_or_default()] } else { schema["type"].members().filter_map(JsonValue::as_str).collect() };
+  if schema.has_key("type") && !types.iter().any(|x| has_type(instance, x)) {
+    output.push(format!("{path}: expected type {} got {}", types.join(" or "), type_name(instance)));
+    return output;
+  }
+
+  if schema.has_key("const") && schema["const"] != *instance {
+    output.push(format!("{path}: expected {} got {}", describe_json(&schema["const"]), describe_json(instance)));
+  }
+  if schema["enum"].is_array() && !schema["enum"].members().any(|x| x == instance) {
+    output.push(format!("{path}: expected one of {} got {}", describe_json(&schema["enum"]), describe_json(instance)));
+  }
+
+  if instance.is_string() {
+    let text = instance.as_str().unwrap_or_default();
+    let length = text.chars().count();
+    if schema["minLength"].as_usize().is_some_and(|x| length < x) {
+      output.push(format!("{path}: expected at least {} characters got {length}", schema["minLength"]));
+    }
+    if schema["maxLength"].as_usize().is_some_and(|x| length > x) {
+      output.push(format!("{path}: expected at most {} characters got {length}", schema["maxLength"]));
+    }
+    if schema["pattern"].is_string() && !Regex::new(schema["pattern"].as_str().unwrap_or_default()).is_ok_and(|x| x.is_match(text)) {
+      output.push(format!("{path}: expected a string matching /{}/ got {}", schema["pattern"], describe_json(instance)));
+    }
+  }
+
+  if instance.is_number() {
+    let number = instance.as_f64().unwrap_or_default();
+    if schema["minimum"].as_f64().is_some_and(|x| number < x) {
+      output.push(format!("{path}: expected at least {} got {instance}", schema["minimum"]));
+    }
+    if schema["maximum"].as_f64().is_some_and(|x| number > x) {
+      output.push(format!("{path}: expected at most {} got {instance}", schema["maximum"]));
+    }
+    if schema["exclusiveMinimum"].as_f64().is_some_and(|x| number <= x) {
+      output.push(format!("{path}: expected more than {} got {instance}", schema["exclusiveMinimum"]));
+    }
+    if schema["exclusiveMaximum"].as_f64().is_some_and(|x| number >= x) {
+      output.push(format!("{path}: expected less than {} got {instance}", schema["exclusiveMaximum"]));
+    }
+  }
+
+  if instance.is_array() {
+    if schema["minItems"].as_usize().is_some_and(|x| instance.len() < x) {
+      output.push(format!("{path}: expected at least {} elements got {}", schema["minItems"], instance.len()));
+    }
+    if schema["maxItems"].as_usize().is_some_and(|x| instance.len() > x) {
+      output.push(format!("{path}: expected at most {} elements got {}", schema["maxItems"], instance.len()));
+    }
+    if schema.has_key("items") {
+      for (i, member) in instance.members().enumerate() {
+        output.append(&mut validate_at(root, &schema["items"], member, &index_path(path, i), depth + 1));
+      }
+    }
+  }
+
+  if instance.is_object() {
+    for required in schema["required"].members().filter_map(JsonValue::as_str) {
+      if !instance.has_key(required) {
+        output.push(format!("{}: missing required key", key_path(path, required)));
+      }
+    }
+    for (key, value) in instance.entries() {
+      if schema["properties"].has_key(key) {
+        output.append(&mut validate_at(root, &schema["properties"][key], value, &key_path(path, key), depth + 1));
+      } else if schema["additionalProperties"].as_bool() == Some(false) {
+        output.push(format!("{}: unexpected key", key_path(path, key)));
+      } else if schema.has_key("additionalProperties") {
+        output.append(&mut validate_at(root, &schema["additionalProperties"], value, &key_path(path, key), depth + 1));
+      }
+    }
+  }
+
+  return output;
+}
+
+//integer also matches numbers like 1.0, that json cant tell apart from 1
+fn has_type(instance: &JsonValue, expected_type: &str) -> bool {
+  return match expected_type {
+    "number" => instance.is_number(),
+    other => type_name(instance) == other,
+  };
+}
+
+//Only references within the schema like # or #/$defs/name are supported
+pub fn resolve_ref<'a>(root: &'a JsonValue, reference: &str) -> Option<&'a JsonValue> {
+  let pointer = reference.strip_prefix('#')?;
+  if !pointer.is_empty() && !pointer.starts_with('/') {
+    return None;
+  }
+  let mut value = root;
+  for token in pointer.split('/').skip(1) {
+    let token = token.replace("~1", "/").replace("~0", "~");
+    value = if value.is_array() { value.members().nth(token.parse().ok()?)? } else if value.has_key(&token) { &value[token.as_str()] } else { return None };
+  }
+  return Some(value);
+}
+
+//Replaces paths to schema files in body_matches_schema with the schema they contain, relative paths start at the directory of the config file
+//Returns the path in the config and why for every file that couldnt be loaded, those paths are left in place
+pub fn load_files(config: &mut JsonValue, directory: &Path) -> Vec<(String, String)> {
+  let mut errors: Vec<(String, String)> = Vec::new();
+  if !config["tests"].is_array() {
+    return errors;
+  }
+
+  for (i, test_chain) in config["tests"].members_mut().enumerate() {
+    let test_chain_path = index_path("$.tests", i);
+    if test_chain["defaults"]["retry"]["until"].is_object() {
+      load_file(&mut test_chain["defaults"]["retry"]["until"], &format!("{test_chain_path}.defaults.retry.until"), directory, &mut errors);
+    }
+    if !test_chain["tests"].is_array() {
+      continue;
+    }
+    for (j, test) in test_chain["tests"].members_mut().enumerate() {
+      let test_path = index_path(&format!("{test_chain_path}.tests"), j);
+      if test["expected_outcome"].is_object() {
+        load_file(&mut test["expected_outcome"], &format!("{test_path}.expected_outcome"), directory, &mut errors);
+      }
+      if test["retry"]["until"].is_object() {
+        load_file(&mut test["retry"]["until"], &format!("{test_path}.retry.until"), directory, &mut errors);
+      }
+    }
+  }
+
+  return errors;
+}
+
+fn load_file(outcome: &mut JsonValue, path: &str, directory: &Path, errors: &mut Vec<(String, String)>) {
+  let Some(file) = outcome["body_matches_schema"].as_str().map(String::from) else {
+    return;
+  };
+  let path = key_path(path, "body_matches_schema");
+  let schema = match fs::read_to_string(directory.join(&file)) {
+    Ok(x) => x,
+    Err(e) => {
+      errors.push((path, format!("failed to read schema file {file}: {e}")));
+      return;
+    },
+  };
+  match jzon::parse(&schema) {
+    Ok(x) => outcome["body_matches_schema"] = x,
+    Err(e) => errors.push((path, format!("schema file {file} isnt valid json: {e}"))),
+  }
+}
@@ -5,8 +5,8 @@ mod stringify_test_outcome {
 
 	#[test]
 	fn test_ok() {
-		let actual_outcome: TestOutcome = Default::default();
-		let res = stringify_test_outcome(&actual_outcome, &actual_outcome, "", "test_chain", "test");
+		let actual_outcome: TestOutcome = TestOutcome::default();
+		let res = stringify_test_outcome(&TestFailure::default(), &actual_outcome, "test_chain", "test");
 
 		assert_eq!(res, "");
 	}
@@ -15,15 +15,31 @@ mod stringify_test_outcome {
 	fn response_body_different() {
 		let actual_outcome = TestOutcome {
 			body_equals: Some("hello world".to_string()),
+			body_contains: None,
 			status_code_equals: None,
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
 		let expected_outcome = TestOutcome {
 			body_equals: Some("Hello, world!".to_string()),
+			body_contains: None,
 			status_code_equals: None,
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
-		let res = stringify_test_outcome(&actual_outcome, &expected_outcome, "Hello, world!", "test_chain", "test");
+		let res = stringify_test_outcome(&TestFailure { actual_outcome, response_body: "Hello, world!".to_string(), ..TestFailure::default() }, &expected_outcome, "test_chain", "test");
 
 		assert_eq!(res, "Test \u{1b}[96mtest_chain\u{1b}[0m: \u{1b}[96mtest\u{1b}[0m \u{1b}[91mfailed\u{1b}[0m:\n\u{1b}[91mresponse body of\nhello world\ndidnt match expected outcome\nHello, world!\n\u{1b}[0m");
 	}
@@ -32,15 +48,31 @@ mod stringify_test_outcome {
 	fn status_code_different() {
 		let actual_outcome = TestOutcome {
 			body_equals: None,
+			body_contains: None,
 			status_code_equals: Some(400),
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
 		let expected_outcome = TestOutcome {
 			body_equals: None,
+			body_contains: None,
 			status_code_equals: Some(200),
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
-		let res = stringify_test_outcome(&actual_outcome, &expected_outcome, "Hello, world!", "test_chain", "test");
+		let res = stringify_test_outcome(&TestFailure { actual_outcome, response_body: "Hello, world!".to_string(), ..TestFailure::default() }, &expected_outcome, "test_chain", "test");
 
 		assert_eq!(res, "Test \u{1b}[96mtest_chain\u{1b}[0m: \u{1b}[96mtest\u{1b}[0m \u{1b}[91mfailed\u{1b}[0m:\n\u{1b}[91mresponse status code of 400 didnt match expected outcome 200\n\u{1b}[0m\u{1b}[95mresponse body was Hello, world!\n\u{1b}[0m");
 	}
@@ -49,30 +81,333 @@ mod stringify_test_outcome {
 	fn status_code_and_body_different() {
 		let actual_outcome = TestOutcome {
 			body_equals: Some("hello world".to_string()),
+			body_contains: None,
 			status_code_equals: Some(400),
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
 		let expected_outcome = TestOutcome {
 			body_equals: Some("Hello, world!".to_string()),
+			body_contains: None,
 			status_code_equals: Some(200),
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
 		};
 
-		let res = stringify_test_outcome(&actual_outcome, &expected_outcome, "Hello, world!", "test_chain", "test");
+		let res = stringify_test_outcome(&TestFailure { actual_outcome, response_body: "Hello, world!".to_string(), ..TestFailure::default() }, &expected_outcome, "test_chain", "test");
 
 		assert_eq!(res, "Test \u{1b}[96mtest_chain\u{1b}[0m: \u{1b}[96mtest\u{1b}[0m \u{1b}[91mfailed\u{1b}[0m:\n\u{1b}[91mresponse body of\nhello world\ndidnt match expected outcome\nHello, world!\n\u{1b}[0m\u{1b}[91mresponse status code of 400 didnt match expected outcome 200\n\u{1b}[0m");
 	}
+
+	#[test]
+	fn response_body_diff() {
+		let actual_outcome = TestOutcome {
+			body_equals: Some(r#"[{"id":3,"tag_ids":[2]}]"#.to_string()),
+			body_contains: None,
+			status_code_equals: None,
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
+		};
+
+		let expected_outcome = TestOutcome {
+			body_equals: Some(r#"[{"id":3,"tag_ids":[]}]"#.to_string()),
+			body_contains: None,
+			status_code_equals: None,
+			headers_equal: None,
+			headers_contain: None,
+			headers_absent: None,
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
+		};
+
+		let res = stringify_test_outcome(&TestFailure { actual_outcome, body_diff: vec!["$[0].tag_ids: expected [] got [2]".to_string()], ..TestFailure::default() }, &expected_outcome, "test_chain", "test");
+
+		assert_eq!(res, "Test \u{1b}[96mtest_chain\u{1b}[0m: \u{1b}[96mtest\u{1b}[0m \u{1b}[91mfailed\u{1b}[0m:\n\u{1b}[91mresponse body didnt match expected outcome:\n  $[0].tag_ids: expected [] got [2]\n\u{1b}[0m");
+	}
+
+	#[test]
+	fn headers_different() {
+		let actual_outcome = TestOutcome {
+			body_equals: None,
+			body_contains: None,
+			status_code_equals: None,
+			headers_equal: Some(vec![("Content-Type".to_string(), "text/html".to_string())].into_iter().collect()),
+			headers_contain: Some(LinkedHashMap::new()),
+			headers_absent: Some(["Set-Cookie".to_string()].into_iter().collect()),
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
+		};
+
+		let expected_outcome = TestOutcome {
+			body_equals: None,
+			body_contains: None,
+			status_code_equals: None,
+			headers_equal: Some(vec![("Content-Type".to_string(), "application/json".to_string())].into_iter().collect()),
+			headers_contain: Some(vec![("Location".to_string(), "/api/v1/".to_string())].into_iter().collect()),
+			headers_absent: Some(["Set-Cookie".to_string()].into_iter().collect()),
+			assertions: None,
+			max_duration_ms: None,
+			body_matches_snapshot: None,
+			body_matches_schema: None,
+		};
+
+		let res = stringify_test_outcome(&TestFailure { actual_outcome, response_body: "Hello, world!".to_string(), ..TestFailure::default() }, &expected_outcome, "test_chain", "test");
+
+		assert_eq!(res, "Test \u{1b}[96mtest_chain\u{1b}[0m: \u{1b}[96mtest\u{1b}[0m \u{1b}[91mfailed\u{1b}[0m:\n\u{1b}[91mresponse header Content-Type of text/html didnt match expected outcome application/json\n\u{1b}[0m\u{1b}[91mresponse header Location was missing, expected it to contain /api/v1/\n\u{1b}[0m\u{1b}[91mresponse header Set-Cookie was present, expected it to be absent\n\u{1b}[0m");
+	}
+}
+
+mod check_test_result {
+	use super::*;
+
+	fn test_with_outcome(expected_outcome: TestOutcome) -> Test {
+		return Test {
+			name: "test".to_string(),
+			endpoint: "/".to_string(),
+			method: "GET".to_string(),
+			body: None,
+			cookies: None,
+			headers: None,
+			before: None,
+			capture: None,
+			expected_outcome,
+			skip: false,
+			tags: Vec::new(),
+			timeout_ms: None,
+			retry: None,
+		};
+	}
+
+	#[test]
+	fn headers_match_case_insensitive() {
+		let test = test_with_outcome(TestOutcome {
+			headers_equal: Some(vec![("Content-Type".to_string(), "application/json".to_string())].into_iter().collect()),
+			headers_contain: Some(vec![("Cache-Control".to_string(), "no-store".to_string())].into_iter().collect()),
+			headers_absent: Some(["Set-Cookie".to_string()].into_iter().collect()),
+			assertions: None,
+			..TestOutcome::default()
+		});
+		let response_headers: LinkedHashMap<String, String> = vec![
+			("content-type".to_string(), "application/json".to_string()),
+			("cache-control".to_string(), "private, no-store".to_string()),
+		].into_iter().collect();
+
+		assert!(check_test_result(&test, 200, "application/json", &response_headers, "{}", Duration::ZERO, None, &HashMap::new()) == TestResults::Passed);
+	}
+
+	#[test]
+	fn headers_mismatch() {
+		let test = test_with_outcome(TestOutcome {
+			headers_equal: Some(vec![("Content-Type".to_string(), "application/json".to_string())].into_iter().collect()),
+			headers_absent: Some(["Set-Cookie".to_string()].into_iter().collect()),
+			assertions: None,
+			..TestOutcome::default()
+		});
+		let response_headers: LinkedHashMap<String, String> = vec![
+			("content-type".to_string(), "text/html".to_string()),
+			("set-cookie".to_string(), "a=b".to_string()),
+		].into_iter().collect();
+
+		assert!(check_test_result(&test, 200, "text/html", &response_headers, "", Duration::ZERO, None, &HashMap::new()) == TestResults::Failed(TestFailure {
+			actual_outcome: TestOutcome {
+				headers_equal: Some(vec![("Content-Type".to_string(), "text/html".to_string())].into_iter().collect()),
+				headers_absent: Some(["Set-Cookie".to_string()].into_iter().collect()),
+				assertions: None,
+				..TestOutcome::default()
+			},
+			..TestFailure::default()
+		}));
+	}
+
+	#[test]
+	fn slow_response_fails_max_duration() {
+		let test = test_with_outcome(TestOutcome {
+			max_duration_ms: Some(500),
+			..TestOutcome::default()
+		});
+
+		assert!(check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), "{}", Duration::from_millis(500), None, &HashMap::new()) == TestResults::Passed);
+		let TestResults::Failed(failure) = check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), "{}", Duration::from_millis(1234), None, &HashMap::new()) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.actual_outcome.max_duration_ms, Some(1234));
+		assert_eq!(stringify_test_outcome(&failure, &test.expected_outcome, "chain", "slow"), "Test \x1b[96mchain\x1b[0m: \x1b[96mslow\x1b[0m \x1b[91mfailed\x1b[0m:\n\x1b[91mresponse took 1234ms, expected at most 500ms\n\x1b[0m");
+	}
+
+	#[test]
+	fn describe_failure_without_colors() {
+		let test = test_with_outcome(TestOutcome {
+			status_code_equals: Some(200),
+			..TestOutcome::default()
+		});
+
+		let TestResults::Failed(failure) = check_test_result(&test, 500, "text/html", &LinkedHashMap::new(), "<html></html>", Duration::ZERO, None, &HashMap::new()) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(describe_failure(&failure, &test.expected_outcome, false), "response status code of 500 didnt match expected outcome 200\nresponse body was <html></html>\n");
+
+		let errored = TestFailure { error: Some("request timed out after 1000ms".to_string()), ..TestFailure::default() };
+		assert_eq!(describe_failure(&errored, &test.expected_outcome, false), "request timed out after 1000ms\n");
+		assert_eq!(stringify_test_outcome(&errored, &test.expected_outcome, "chain", "slow"), "Test \x1b[96mchain\x1b[0m: \x1b[96mslow\x1b[0m \x1b[91mfailed\x1b[0m:\n\x1b[91mrequest timed out after 1000ms\n\x1b[0m");
+	}
+
+	#[test]
+	fn assertions_check_the_body_of_this_response() {
+		let test = test_with_outcome(TestOutcome {
+			assertions: Some(vec![Assertion { path: "$.id".to_string(), operator: assertion::AssertionOperator::Exists, value: None }]),
+			..TestOutcome::default()
+		});
+		let earlier_attempt: HashMap<String, JsonValue> = vec![("test".to_string(), jzon::parse(r#"{"id":1}"#).unwrap())].into_iter().collect();
+
+		assert!(check_test_result(&test, 200, "application/problem+json", &LinkedHashMap::new(), r#"{"id":1}"#, Duration::ZERO, None, &HashMap::new()) == TestResults::Passed);
+		let TestResults::Failed(failure) = check_test_result(&test, 502, "text/html", &LinkedHashMap::new(), "<html></html>", Duration::ZERO, None, &earlier_attempt) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.failed_assertions, vec!["$.id: response body isnt json".to_string()]);
+	}
+
+	#[test]
+	fn changed_body_fails_snapshot() {
+		let test = test_with_outcome(TestOutcome {
+			body_matches_snapshot: Some(crate::Snapshot { redact: vec!["$.created_at".to_string()] }),
+			..TestOutcome::default()
+		});
+		let snapshot = jzon::parse(r#"{"id":1,"name":"test","created_at":"%%%ANY%%%"}"#).unwrap();
+
+		assert!(check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":1,"name":"test","created_at":"2024-01-01"}"#, Duration::ZERO, Some(&snapshot), &HashMap::new()) == TestResults::Passed);
+		assert!(check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":2}"#, Duration::ZERO, None, &HashMap::new()) == TestResults::Passed);
+		let TestResults::Failed(failure) = check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":2,"name":"test","created_at":"2024-01-02"}"#, Duration::ZERO, Some(&snapshot), &HashMap::new()) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.snapshot_diff, vec!["$.id: expected 1 got 2".to_string()]);
+		assert_eq!(stringify_test_outcome(&failure, &test.expected_outcome, "chain", "snapshot"), "Test \x1b[96mchain\x1b[0m: \x1b[96msnapshot\x1b[0m \x1b[91mfailed\x1b[0m:\n\x1b[91mresponse body didnt match snapshot, rerun with --update-snapshots if the change is intended:\n  $.id: expected 1 got 2\n\x1b[0m");
+	}
+
+	#[test]
+	fn rewritten_snapshot_only_drops_snapshot_mismatch() {
+		let test = test_with_outcome(TestOutcome {
+			status_code_equals: Some(200),
+			body_matches_snapshot: Some(crate::Snapshot { redact: Vec::new() }),
+			..TestOutcome::default()
+		});
+		let snapshot = jzon::parse(r#"{"id":1}"#).unwrap();
+
+		let stale = check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":2}"#, Duration::ZERO, Some(&snapshot), &HashMap::new());
+		assert!(without_snapshot_mismatch(stale) == TestResults::Passed);
+		let stale_and_wrong_status = check_test_result(&test, 500, "application/json", &LinkedHashMap::new(), r#"{"id":2}"#, Duration::ZERO, Some(&snapshot), &HashMap::new());
+		let TestResults::Failed(failure) = without_snapshot_mismatch(stale_and_wrong_status) else {
+			panic!("expected the test to fail");
+		};
+		assert!(failure.snapshot_diff.is_empty());
+		assert_eq!(failure.actual_outcome.body_matches_snapshot, None);
+		assert_eq!(failure.actual_outcome.status_code_equals, Some(500));
+	}
+
+	#[test]
+	fn body_not_matching_schema_fails_test() {
+		let test = test_with_outcome(TestOutcome {
+			body_matches_schema: Some(r#"{"type":"object","required":["id"]}"#.to_string()),
+			..TestOutcome::default()
+		});
+
+		assert!(check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":1}"#, Duration::ZERO, None, &HashMap::new()) == TestResults::Passed);
+		let TestResults::Failed(failure) = check_test_result(&test, 200, "text/plain", &LinkedHashMap::new(), "id", Duration::ZERO, None, &HashMap::new()) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.schema_errors, vec!["$: response body isnt json".to_string()]);
+		let TestResults::Failed(failure) = check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"name":"test"}"#, Duration::ZERO, None, &HashMap::new()) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.actual_outcome.body_matches_schema, Some(r#"{"name":"test"}"#.to_string()));
+		assert_eq!(stringify_test_outcome(&failure, &test.expected_outcome, "chain", "schema"), "Test \x1b[96mchain\x1b[0m: \x1b[96mschema\x1b[0m \x1b[91mfailed\x1b[0m:\n\x1b[91mresponse body didnt match schema:\n  $.id: missing required key\n\x1b[0m");
+	}
+}
+
+mod run_test_before_tasks {
+	use super::*;
+	use crate::http_request::unit_test::config_for;
+	use crate::Task;
+	use linked_hash_set::LinkedHashSet;
+	use std::net::TcpListener;
+
+	fn test_with_before(before: &[&str]) -> Test {
+		return Test {
+			name: "test".to_string(),
+			endpoint: "/".to_string(),
+			method: "GET".to_string(),
+			body: None,
+			cookies: None,
+			headers: None,
+			before: Some(before.iter().map(|x| (*x).to_string()).collect()),
+			capture: None,
+			expected_outcome: TestOutcome::default(),
+			skip: false,
+			tags: Vec::new(),
+			timeout_ms: None,
+			retry: None,
+		};
+	}
+
+	#[test]
+	fn timed_out_task_fails_with_its_name() {
+		//the listener accepts connections but never answers
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let config = config_for(&listener);
+		let task = Task { endpoint: "/token".to_string(), method: "GET".to_string(), body: None, headers: None, capture: None, timeout_ms: Some(50) };
+		let config_file = ConfigFile {
+			configs: LinkedHashSet::new(),
+			tasks: vec![("login".to_string(), task)].into_iter().collect(),
+			tests: LinkedHashSet::new(),
+		};
+
+		let res = run_test_before_tasks(&test_with_before(&["login"]), &config, &config_file, &mut HashMap::new());
+		assert_eq!(res, Err("task login: request timed out after 50ms".to_string()));
+	}
+
+	#[test]
+	fn undefined_task_fails_instead_of_panicking() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let config_file = ConfigFile { configs: LinkedHashSet::new(), tasks: LinkedHashMap::new(), tests: LinkedHashSet::new() };
+
+		let res = run_test_before_tasks(&test_with_before(&["login"]), &config_for(&listener), &config_file, &mut HashMap::new());
+		assert_eq!(res, Err("task login not found".to_string()));
+	}
 }
 
 mod expected_equals_actual_json {
 	use super::*;
 	use jzon::*;
 
+	fn expected_equals_actual_json(expected: &JsonValue, actual: &JsonValue, test_responses: &HashMap<String, JsonValue>) -> bool {
+		return diff_json(expected, actual, test_responses, "$", MatchMode::Exact).is_empty();
+	}
+
 	#[test]
 	fn same_null() {
 		let expected: JsonValue = JsonValue::Null;
 		let actual: JsonValue = JsonValue::Null;
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -80,7 +415,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Number(5.into());
 		let actual: JsonValue = JsonValue::Number(5.into());
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -88,7 +423,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Number(0.into());
 		let actual: JsonValue = JsonValue::Number(0.into());
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -96,7 +431,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Number(5.into());
 		let actual: JsonValue = JsonValue::Number(4.into());
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -104,7 +439,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::String("Hello World!".to_string());
 		let actual: JsonValue = JsonValue::String("Hello World!".to_string());
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -112,7 +447,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::String("Hello Universe!".to_string());
 		let actual: JsonValue = JsonValue::String("Hello World!".to_string());
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -120,7 +455,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(Vec::new());
 		let actual: JsonValue = JsonValue::Array(Vec::new());
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -128,7 +463,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 		let actual: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -136,7 +471,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(vec![JsonValue::String("Moin World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 		let actual: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -144,7 +479,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 		let actual: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string())]);
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -152,7 +487,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string())]);
 		let actual: JsonValue = JsonValue::Array(vec![JsonValue::String("Hello World!".to_string()), JsonValue::String("Hello Universe!".to_string())]);
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -160,7 +495,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! {};
 		let actual: JsonValue = object! {};
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -168,7 +503,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -176,7 +511,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { string: "Hello World!", number: 42, boolean: true};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -184,7 +519,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!"};
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -192,7 +527,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { number: 42, string: "Hello World!"};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -200,7 +535,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { number: 42, string: "Hello World!", bool: true};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -208,7 +543,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = object! { number: 42, string: "Hello World!", boolean: false};
 		let actual: JsonValue = object! { number: 42, string: "Hello World!", boolean: true};
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -216,10 +551,11 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = JsonValue::Array(vec![object! { number: 42, string: "Hello World!", boolean: false}, object! { number: 42, string: "Hello World!", boolean: true}]);
 		let actual: JsonValue = JsonValue::Array(vec![object! { number: 42, string: "Hello World!", boolean: false}, object! { number: 42, string: "Hello World!", boolean: true}]);
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
+	#[allow(clippy::too_many_lines)]
 	fn complex_object() {
 		let expected: JsonValue = object! {
 			"configs": [
@@ -614,10 +950,11 @@ mod expected_equals_actual_json {
 			]
 		};
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
+	#[allow(clippy::too_many_lines)]
 	fn complex_object_tiny_difference() {
 		let expected: JsonValue = object! {
 			"configs": [
@@ -1012,7 +1349,7 @@ mod expected_equals_actual_json {
 			]
 		};
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -1020,7 +1357,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = parse(r#"{"datasets":[{"label":"Earning","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 		let actual: JsonValue = parse(r#"{"datasets":[{"label":"Earning","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -1028,7 +1365,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = parse(r#"{"datasets":[{"label":"%%%ANY%%%","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 		let actual: JsonValue = parse(r#"{"datasets":[{"label":"Earning","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -1036,7 +1373,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = parse(r#"{"datasets":[{"label":"%%%ANY_STRING%%%","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 		let actual: JsonValue = parse(r#"{"datasets":[{"label":"Earning","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 
-		assert!(expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -1044,7 +1381,7 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = parse(r#"{"datasets":[{"label":"%%%ANY_STRING%%%","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 		let actual: JsonValue = parse(r#"{"datasets":[{"label":100,"data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
 	}
 
 	#[test]
@@ -1052,6 +1389,296 @@ mod expected_equals_actual_json {
 		let expected: JsonValue = parse(r#"{"datasets":[{"label":"%%%ANY_STRING%%%","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 		let actual: JsonValue = parse(r#"{"datasets":[{"label":null,"data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.9,"label":"246.90€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Net","data":[{"name":null,"timestamp":"1923-03-01","value":6.0,"label":"3.00€ 3.00$"},{"name":null,"timestamp":"2023-03-01","value":266.0,"label":"246.00€ 20.00$"},{"name":null,"timestamp":"2123-03-01","value":6.0,"label":"3.00€ 3.00$"}]},{"label":"Spending","data":[{"name":null,"timestamp":"2023-03-01","value":-0.9,"label":"-0.90€"}]}]}"#).unwrap();
 
-		assert!(!expected_equals_actual_json(expected, actual, HashMap::new()));
+		assert!(!expected_equals_actual_json(&expected, &actual, &HashMap::new()));
+	}
+}
+
+mod diff_json {
+	use super::*;
+	use jzon::*;
+
+	fn diff(expected: &str, actual: &str) -> Vec<String> {
+		return diff_json(&parse(expected).unwrap(), &parse(actual).unwrap(), &HashMap::new(), "$", MatchMode::Exact);
+	}
+
+	#[test]
+	fn same() {
+		assert_eq!(diff(r#"[{"id":0,"tag_ids":[]}]"#, r#"[{"id":0,"tag_ids":[]}]"#), Vec::<String>::new());
+	}
+
+	#[test]
+	fn nested_array_different() {
+		assert_eq!(diff(r#"[{"id":0},{"id":1},{"id":2},{"id":3,"tag_ids":[]}]"#, r#"[{"id":0},{"id":1},{"id":2},{"id":3,"tag_ids":[2]}]"#), vec!["$[3].tag_ids: expected [] got [2]"]);
+	}
+
+	#[test]
+	fn missing_and_unexpected_keys() {
+		assert_eq!(diff(r#"{"id":0,"name":"a"}"#, r#"{"id":0,"extra":true}"#), vec!["$.name: missing key", "$.extra: unexpected key"]);
+	}
+
+	#[test]
+	fn different_values_and_types() {
+		assert_eq!(diff(r#"{"name":"Euro","id":"%%%ANY_STRING%%%","list":[1]}"#, r#"{"name":"Eur","id":5,"list":{}}"#), vec![
+			"$.name: expected \"Euro\" got \"Eur\"",
+			"$.id: expected \"%%%ANY_STRING%%%\" got 5",
+			"$.list: expected [1] got {}",
+		]);
+	}
+
+	#[test]
+	fn long_values_are_shortened() {
+		let long_array = format!("[{}]", (0..50).map(|x| x.to_string()).collect::<Vec<String>>().join(","));
+		assert_eq!(diff(&long_array, "[]"), vec!["$: expected an array with 50 elements got []"]);
+	}
+
+	#[test]
+	fn reference_different() {
+		let test_responses: HashMap<String, JsonValue> = vec![("create".to_string(), parse(r#"[{"id":7}]"#).unwrap())].into_iter().collect();
+		assert_eq!(diff_json(&parse(r#"{"id":"%%%[[[create]]]...[[[id]]]%%%"}"#).unwrap(), &parse(r#"{"id":8}"#).unwrap(), &test_responses, "$", MatchMode::Exact), vec!["$.id: expected 7 from %%%[[[create]]]...[[[id]]]%%% got 8"]);
+	}
+}
+
+mod diff_json_subset {
+	use super::*;
+	use jzon::*;
+
+	fn diff(expected: &str, actual: &str) -> Vec<String> {
+		return diff_json(&parse(expected).unwrap(), &parse(actual).unwrap(), &HashMap::new(), "$", MatchMode::Subset);
+	}
+
+	#[test]
+	fn additional_keys_and_elements_are_ignored() {
+		assert_eq!(diff(r#"{"items":[{"id":1}],"total":"%%%ANY%%%"}"#, r#"{"items":[{"id":0,"name":"a"},{"id":1,"name":"b"}],"total":2,"page":1}"#), Vec::<String>::new());
+	}
+
+	#[test]
+	fn missing_key() {
+		assert_eq!(diff(r#"{"id":0,"name":"a"}"#, r#"{"id":0}"#), vec!["$.name: missing key"]);
+	}
+
+	#[test]
+	fn missing_element() {
+		assert_eq!(diff(r#"[{"id":1},{"id":5}]"#, r#"[{"id":0},{"id":1}]"#), vec!["$[1]: no element of the actual array matched {\"id\":5}"]);
+	}
+
+	#[test]
+	fn nested_difference() {
+		assert_eq!(diff(r#"{"user":{"role":"admin"}}"#, r#"{"user":{"role":"user","name":"a"}}"#), vec!["$.user.role: expected \"admin\" got \"user\""]);
+	}
+}
+
+mod assertions {
+	use super::*;
+	use crate::Assertion;
+	use assertion::AssertionOperator;
+
+	fn check(path: &str, operator: AssertionOperator, value: Option<&str>, body: &str) -> Option<String> {
+		let assertion = Assertion { path: path.to_string(), operator, value: value.map(String::from) };
+		return assertion::check(&assertion, &jzon::parse(body).unwrap(), &HashMap::new());
 	}
-}
\ No newline at end of file
+
+	const BODY: &str = r#"{"items":[{"id":1,"tags":["a"]},{"id":2,"tags":[]}],"user":{"role":"admin","email":"admin@example.com"},"total":2.5,"created":"2023-03-01"}"#;
+
+	#[test]
+	fn passing_assertions() {
+		assert_eq!(check("$.items.length", AssertionOperator::Equals, Some("2"), BODY), None);
+		assert_eq!(check("$.user.role", AssertionOperator::NotEquals, Some(r#""user""#), BODY), None);
+		assert_eq!(check("$.total", AssertionOperator::Gt, Some("2"), BODY), None);
+		assert_eq!(check("$.created", AssertionOperator::Lt, Some(r#""2024-01-01""#), BODY), None);
+		assert_eq!(check("$.items", AssertionOperator::Contains, Some(r#"{"id":2}"#), BODY), None);
+		assert_eq!(check("$.user.email", AssertionOperator::Contains, Some(r#""@example""#), BODY), None);
+		assert_eq!(check("$.user.email", AssertionOperator::Matches, Some(r#""^[a-z]+@""#), BODY), None);
+		assert_eq!(check("$.items[0].tags[0]", AssertionOperator::Exists, None, BODY), None);
+		assert_eq!(check("$.items[1].tags[0]", AssertionOperator::NotExists, None, BODY), None);
+		assert_eq!(check("$.items[-1].id", AssertionOperator::TypeIs, Some(r#""integer""#), BODY), None);
+		assert_eq!(check("$.total", AssertionOperator::TypeIs, Some(r#""number""#), BODY), None);
+		assert_eq!(check("$.user.role", AssertionOperator::Equals, Some(r#""%%%ANY_STRING%%%""#), BODY), None);
+	}
+
+	#[test]
+	fn failing_assertions() {
+		assert_eq!(check("$.items.length", AssertionOperator::Equals, Some("3"), BODY), Some("$.items.length: expected to equal 3 got 2".to_string()));
+		assert_eq!(check("$.total", AssertionOperator::Lt, Some("1"), BODY), Some("$.total: expected to be less than 1 got 2.5".to_string()));
+		assert_eq!(check("$.total", AssertionOperator::Gt, Some(r#""1""#), BODY), Some("$.total: expected to be greater than \"1\" got 2.5".to_string()));
+		assert_eq!(check("$.items", AssertionOperator::Contains, Some(r#"{"id":3}"#), BODY), Some("$.items: expected to contain {\"id\":3} got [{\"id\":1,\"tags\":[\"a\"]},{\"id\":2,\"tags\":[]}]".to_string()));
+		assert_eq!(check("$.user.email", AssertionOperator::Matches, Some(r#""^root""#), BODY), Some("$.user.email: expected to match \"^root\" got \"admin@example.com\"".to_string()));
+		assert_eq!(check("$.user.name", AssertionOperator::Exists, None, BODY), Some("$.user.name: expected to exist".to_string()));
+		assert_eq!(check("$.user", AssertionOperator::NotExists, None, BODY), Some("$.user: expected not to exist got {\"role\":\"admin\",\"email\":\"admin@example.com\"}".to_string()));
+		assert_eq!(check("$.total", AssertionOperator::TypeIs, Some(r#""integer""#), BODY), Some("$.total: expected type integer got number".to_string()));
+		assert_eq!(check("$.user.name", AssertionOperator::Equals, Some(r#""a""#), BODY), Some("$.user.name: expected to equal \"a\" but it doesnt exist".to_string()));
+	}
+
+	#[test]
+	fn every_failed_assertion_is_reported() {
+		let test = Test {
+			name: "a".to_string(),
+			endpoint: "/".to_string(),
+			method: "GET".to_string(),
+			body: None,
+			cookies: None,
+			headers: None,
+			before: None,
+			capture: None,
+			expected_outcome: TestOutcome {
+				assertions: Some(vec![
+					Assertion { path: "$.id".to_string(), operator: AssertionOperator::Equals, value: Some("1".to_string()) },
+					Assertion { path: "$.name".to_string(), operator: AssertionOperator::Exists, value: None },
+					Assertion { path: "$.role".to_string(), operator: AssertionOperator::Equals, value: Some(r#""admin""#.to_string()) },
+				]),
+				..TestOutcome::default()
+			},
+			skip: false,
+			tags: Vec::new(),
+			timeout_ms: None,
+			retry: None,
+		};
+		let test_responses: HashMap<String, JsonValue> = vec![("a".to_string(), jzon::parse(r#"{"id":2,"role":"admin"}"#).unwrap())].into_iter().collect();
+
+		let TestResults::Failed(failure) = check_test_result(&test, 200, "application/json", &LinkedHashMap::new(), r#"{"id":2,"role":"admin"}"#, Duration::ZERO, None, &test_responses) else {
+			panic!("expected the test to fail");
+		};
+		assert_eq!(failure.failed_assertions, vec!["$.id: expected to equal 1 got 2", "$.name: expected to exist"]);
+		assert_eq!(failure.actual_outcome.assertions.as_ref().map(Vec::len), Some(2));
+		assert_eq!(stringify_test_outcome(&failure, &test.expected_outcome, "chain", "a"), "Test \x1b[96mchain\x1b[0m: \x1b[96ma\x1b[0m \x1b[91mfailed\x1b[0m:\n\x1b[91massertions on the response body failed:\n  $.id: expected to equal 1 got 2\n  $.name: expected to exist\n\x1b[0m");
+	}
+}
+
+mod matchers {
+	use super::*;
+	use jzon::*;
+
+	fn matches(matcher: &str, actual: &str) -> bool {
+		return matcher::parse(matcher).unwrap().unwrap().matches(&parse(actual).unwrap());
+	}
+
+	#[test]
+	fn typed_wildcards() {
+		assert!(matches("%%%ANY_NUMBER%%%", "1.5") && !matches("%%%ANY_NUMBER%%%", r#""1""#));
+		assert!(matches("%%%ANY_INTEGER%%%", "3") && !matches("%%%ANY_INTEGER%%%", "3.5"));
+		assert!(matches("%%%ANY_BOOL%%%", "false") && !matches("%%%ANY_BOOL%%%", "0"));
+		assert!(matches("%%%ANY_ARRAY%%%", "[]") && !matches("%%%ANY_ARRAY%%%", "{}"));
+		assert!(matches("%%%ANY_OBJECT%%%", r#"{"a":1}"#) && !matches("%%%ANY_OBJECT%%%", "null"));
+		assert!(matches("%%%NOT_NULL%%%", "0") && !matches("%%%NOT_NULL%%%", "null"));
+		assert!(matches("%%%ANY_UUID%%%", r#""67e55044-10b1-426f-9247-bb680e5fe0c8""#) && !matches("%%%ANY_UUID%%%", r#""67e55044""#));
+	}
+
+	#[test]
+	fn formats_and_regular_expressions() {
+		assert!(matches("%%%ISO8601%%%", r#""2024-03-01""#) && matches("%%%ISO8601%%%", r#""2024-03-01T12:30:00.123+01:00""#) && matches("%%%ISO8601%%%", r#""2024-03-01T12:30:00Z""#));
+		assert!(!matches("%%%ISO8601%%%", r#""01.03.2024""#) && !matches("%%%ISO8601%%%", "20240301"));
+		assert!(matches("%%%EMAIL%%%", r#""admin@example.com""#) && !matches("%%%EMAIL%%%", r#""admin@localhost""#));
+		assert!(matches(r"%%%REGEX(^v\d+(\.\d+)*$)%%%", r#""v1.2.3""#) && !matches(r"%%%REGEX(^v\d+$)%%%", r#""v1.2""#));
+	}
+
+	#[test]
+	fn ranges_and_sets() {
+		assert!(matches("%%%BETWEEN(1,100)%%%", "1") && matches("%%%BETWEEN(1, 100)%%%", "99.5") && !matches("%%%BETWEEN(1,100)%%%", "101"));
+		assert!(matches("%%%BETWEEN(-1.5,0)%%%", "-1") && !matches("%%%BETWEEN(1,100)%%%", r#""50""#));
+		assert!(matches("%%%ONE_OF(active, disabled)%%%", r#""disabled""#) && !matches("%%%ONE_OF(active,disabled)%%%", r#""deleted""#));
+		assert!(matches("%%%ONE_OF(1,2)%%%", "2") && matches("%%%ONE_OF(true)%%%", "true") && !matches("%%%ONE_OF(1,2)%%%", "[1]"));
+		assert!(matches("%%%ONE_OF('Doe, John', 'Roe, Jane')%%%", r#""Doe, John""#) && !matches("%%%ONE_OF('Doe, John',Roe)%%%", r#""Doe""#));
+		assert!(matches("%%%ONE_OF(O'Brien,' padded ')%%%", r#"" padded ""#) && matches("%%%ONE_OF(O'Brien)%%%", r#""O'Brien""#));
+		assert!(matcher::parse("%%%ONE_OF('Doe, John)%%%").unwrap().is_err() && matcher::parse("%%%ONE_OF('Doe' John)%%%").unwrap().is_err());
+	}
+
+	#[test]
+	fn only_matchers_are_parsed() {
+		assert!(matcher::parse("ANY").is_none());
+		assert!(matcher::parse("%%%[[[login]]]...[[[id]]]%%%").is_none());
+		assert!(matcher::parse("%%%ANY%%%").unwrap().is_ok());
+		assert!(matcher::parse("%%%ANYTHING%%%").unwrap().is_err());
+	}
+
+	#[test]
+	fn diff_reports_matchers() {
+		let expected = parse(r#"{"id":"%%%ANY_INTEGER%%%","status":"%%%ONE_OF(active,disabled)%%%","created_at":"%%%ISO8601%%%"}"#).unwrap();
+		let actual = parse(r#"{"id":1.5,"status":"active","created_at":"2024-03-01T12:00:00Z"}"#).unwrap();
+		assert_eq!(diff_json(&expected, &actual, &HashMap::new(), "$", MatchMode::Exact), vec!["$.id: expected \"%%%ANY_INTEGER%%%\" got 1.5"]);
+	}
+
+	#[test]
+	fn invalid_matchers_never_match() {
+		let expected = parse(r#"{"name":"%%%REGEX(^[a-z)%%%"}"#).unwrap();
+		let actual = parse(r#"{"name":"%%%REGEX(^[a-z)%%%"}"#).unwrap();
+		let res = diff_json(&expected, &actual, &HashMap::new(), "$", MatchMode::Exact);
+		assert_eq!(res.len(), 1);
+		assert!(res[0].starts_with("$.name: matcher %%%REGEX(^[a-z)%%% has an invalid regular expression"), "{res:?}");
+	}
+}
+
+mod schemas {
+	use super::*;
+	use jzon::*;
+
+	const RECIPIENTS: &str = r##"{
+		"type": "array",
+		"minItems": 1,
+		"items": {"$ref": "#/$defs/recipient"},
+		"$defs": {
+			"recipient": {
+				"type": "object",
+				"required": ["id", "name"],
+				"additionalProperties": false,
+				"properties": {
+					"id": {"type": "integer", "minimum": 0},
+					"name": {"type": "string", "minLength": 1, "pattern": "^[A-Za-z ]+$"},
+					"user_id": {"type": ["integer", "null"]},
+					"status": {"enum": ["active", "disabled"]},
+					"tag_ids": {"type": "array", "items": {"type": "integer"}, "maxItems": 2}
+				}
+			}
+		}
+	}"##;
+
+	fn validate(schema: &str, body: &str) -> Vec<String> {
+		return schema::validate(&parse(schema).unwrap(), &parse(body).unwrap());
+	}
+
+	#[test]
+	fn matching_body() {
+		assert_eq!(validate(RECIPIENTS, r#"[{"id":0,"name":"Default","user_id":null,"tag_ids":[]},{"id":1,"name":"test","user_id":0,"status":"active","tag_ids":[1,2]}]"#), Vec::<String>::new());
+		assert_eq!(validate("true", r#"{"anything":[]}"#), Vec::<String>::new());
+	}
+
+	#[test]
+	fn every_violation_is_reported_with_its_path() {
+		assert_eq!(validate(RECIPIENTS, r#"[{"id":-1,"name":"","user_id":"0","status":"deleted","tag_ids":[1,"2",3],"extra":true},{"name":"test"}]"#), vec![
+			"$[0].id: expected at least 0 got -1",
+			"$[0].name: expected at least 1 characters got 0",
+			"$[0].name: expected a string matching /^[A-Za-z ]+$/ got \"\"",
+			"$[0].user_id: expected type integer or null got string",
+			"$[0].status: expected one of [\"active\",\"disabled\"] got \"deleted\"",
+			"$[0].tag_ids: expected at most 2 elements got 3",
+			"$[0].tag_ids[1]: expected type integer got string",
+			"$[0].extra: unexpected key",
+			"$[1].id: missing required key",
+		]);
+		assert_eq!(validate(RECIPIENTS, "[]"), vec!["$: expected at least 1 elements got 0"]);
+		assert_eq!(validate(RECIPIENTS, r#"{"id":0}"#), vec!["$: expected type array got object"]);
+	}
+
+	#[test]
+	fn recursive_references() {
+		let tree = r##"{"type":"object","properties":{"value":{"type":"number","exclusiveMaximum":10},"children":{"type":"array","items":{"$ref":"#"}}}}"##;
+		assert_eq!(validate(tree, r#"{"value":1,"children":[{"value":2,"children":[{"value":10}]}]}"#), vec!["$.children[0].children[0].value: expected less than 10 got 10"]);
+		assert_eq!(validate(r##"{"$ref":"#"}"##, "1").len(), 1);
+	}
+
+	#[test]
+	fn schema_files_are_loaded_relative_to_config() {
+		let directory = std::env::temp_dir().join(format!("trest_schemas_{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		std::fs::write(directory.join("item.json"), r#"{"type":"object"}"#).unwrap();
+		let mut config = parse(r#"{"tests":[{"name":"a","tests":[
+			{"name":"b","expected_outcome":{"body_matches_schema":"item.json"}},
+			{"name":"c","expected_outcome":{"body_matches_schema":"missing.json"},"retry":{"attempts":2,"until":{"body_matches_schema":"item.json"}}}
+		]}]}"#).unwrap();
+
+		let errors = schema::load_files(&mut config, &directory);
+		std::fs::remove_dir_all(&directory).unwrap();
+		assert_eq!(errors.iter().map(|x| x.0.as_str()).collect::<Vec<&str>>(), vec!["$.tests[0].tests[1].expected_outcome.body_matches_schema"]);
+		assert!(errors[0].1.starts_with("failed to read schema file missing.json: "));
+		assert_eq!(config["tests"][0]["tests"][0]["expected_outcome"]["body_matches_schema"], object! { type: "object" });
+		assert_eq!(config["tests"][0]["tests"][1]["retry"]["until"]["body_matches_schema"], object! { type: "object" });
+	}
+}
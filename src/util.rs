@@ -0,0 +1,31 @@
+//The number of characters that have to be inserted, removed or replaced to turn a into b
+//Used to suggest what was meant when a name isnt known
+pub fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, a_char) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a_char != *b_char);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  return previous[b.len()];
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  #[test]
+  fn edit_distance_counts_changed_characters() {
+    assert_eq!(edit_distance("timeout_ms", "timeout_ms"), 0);
+    assert_eq!(edit_distance("timeout_ms", "timout_ms"), 1);
+    assert_eq!(edit_distance("ANY_NUMBER", "ANY_NUMBR"), 1);
+    assert_eq!(edit_distance("--fail-fast", "--fial-fast"), 2);
+    assert_eq!(edit_distance("", "abc"), 3);
+  }
+}
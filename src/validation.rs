@@ -0,0 +1,1024 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use jzon::JsonValue;
+
+use crate::{http_request, json_path, variables, environment, tags, snapshot, util};
+use crate::json_path::{key_path, index_path};
+use crate::test::assertion::{self, AssertionOperator};
+use crate::test::{matcher, schema};
+
+const READINESS_CONDITIONS: [&str; 10] = ["endpoint_reachable", "endpoint_status", "endpoint_body_contains", "tcp_port_open", "command_succeeds", "file_exists", "log_line_matches", "delay_ms", "all", "any"];
+const STANDARD_METHODS: [&str; 8] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS", "TRACE"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+  pub path: String,
+  pub message: String,
+  pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.location {
+      Some((line, column)) => write!(f, "{} (line {line}, column {column}): {}", self.path, self.message),
+      None => write!(f, "{}: {}", self.path, self.message),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  String,
+  Bool,
+  Integer,
+  Number,
+  Object,
+  Array,
+}
+
+impl Kind {
+  fn matches(self, value: &JsonValue) -> bool {
+    return match self {
+      Kind::String => value.is_string(),
+      Kind::Bool => value.is_boolean(),
+      Kind::Integer => value.as_f64().is_some_and(|x| x.fract() == 0.0),
+      Kind::Number => value.is_number(),
+      Kind::Object => value.is_object(),
+      Kind::Array => value.is_array(),
+    };
+  }
+
+  fn name(self) -> &'static str {
+    return match self {
+      Kind::String => "a string",
+      Kind::Bool => "a boolean",
+      Kind::Integer => "an integer",
+      Kind::Number => "a number",
+      Kind::Object => "an object",
+      Kind::Array => "an array",
+    };
+  }
+}
+
+//Checks the parsed config file for everything parse_config_file would silently accept or that would only fail at runtime
+//Environment variables are interpolated and schema files loaded first, so typed fields like method can use ${VAR:-default}
+//source is the raw text of the config file and only used to look up line and column of the reported paths, directory is where relative schema files are looked up
+//Returns the config that parse_config_file should get and every error found in it
+pub fn validate(input: &JsonValue, source: &str, directory: &Path) -> (JsonValue, Vec<ValidationError>) {
+  let (mut config, mut interpolation_errors) = environment::interpolate(input);
+  interpolation_errors.append(&mut schema::load_files(&mut config, directory));
+
+  let mut validator = Validator {
+    errors: Vec::new(),
+    locations: locate_paths(source),
+    task_names: config["tasks"].entries().map(|(k, _)| k.to_string()).collect(),
+  };
+  for (path, message) in interpolation_errors {
+    validator.error(&path, message);
+  }
+  validator.validate_root(&config);
+
+  return (config, validator.errors);
+}
+
+struct Validator {
+  errors: Vec<ValidationError>,
+  locations: HashMap<String, (usize, usize)>,
+  task_names: HashSet<String>,
+}
+
+impl Validator {
+  fn error(&mut self, path: &str, message: String) {
+    self.errors.push(ValidationError {
+      path: path.to_string(),
+      message,
+      location: self.locations.get(path).copied(),
+    });
+  }
+
+  fn check_keys(&mut self, value: &JsonValue, path: &str, allowed: &[&str]) {
+    for (key, _) in value.entries() {
+      if allowed.contains(&key) {
+        continue;
+      }
+
+      let message = match allowed.iter().find(|x| util::edit_distance(x, key) <= 2) {
+        Some(suggestion) => format!("unknown key \"{key}\", did you mean \"{suggestion}\"?"),
+        None => format!("unknown key \"{key}\", expected one of {}", allowed.join(", ")),
+      };
+      self.error(&key_path(path, key), message);
+    }
+  }
+
+  //returns true if the key is present and has the right type
+  fn required(&mut self, value: &JsonValue, path: &str, key: &str, kind: Kind) -> bool {
+    if value[key].is_null() {
+      self.error(path, format!("missing required key \"{key}\""));
+      return false;
+    }
+    return self.optional(value, path, key, kind);
+  }
+
+  //returns true if the key is present and has the right type
+  fn optional(&mut self, value: &JsonValue, path: &str, key: &str, kind: Kind) -> bool {
+    if value[key].is_null() {
+      return false;
+    }
+    if !kind.matches(&value[key]) {
+      self.error(&key_path(path, key), format!("expected {}", kind.name()));
+      return false;
+    }
+    return true;
+  }
+
+  fn check_status_code(&mut self, value: &JsonValue, path: &str, key: &str) {
+    let status_code = value[key].as_f64().unwrap_or_default();
+    if !(100.0..=599.0).contains(&status_code) {
+      self.error(&key_path(path, key), format!("{status_code} is not a valid HTTP status code"));
+    }
+  }
+
+  fn optional_milliseconds(&mut self, value: &JsonValue, path: &str, key: &str) {
+    if self.optional(value, path, key, Kind::Integer) && value[key].as_f64().unwrap_or_default() < 0.0 {
+      self.error(&key_path(path, key), String::from("expected a duration in milliseconds that isnt negative"));
+    }
+  }
+
+  //A request timeout of 0 would fail every request right away
+  fn optional_request_timeout(&mut self, value: &JsonValue, path: &str) {
+    if self.optional(value, path, "timeout_ms", Kind::Integer) && value["timeout_ms"].as_f64().unwrap_or_default() <= 0.0 {
+      self.error(&key_path(path, "timeout_ms"), String::from("expected a timeout in milliseconds greater than 0"));
+    }
+  }
+
+  fn check_object_values(&mut self, value: &JsonValue, path: &str, kind: Kind) {
+    for (key, entry) in value.entries() {
+      if !kind.matches(entry) {
+        self.error(&key_path(path, key), format!("expected {}", kind.name()));
+      }
+    }
+  }
+
+  fn check_array_members(&mut self, value: &JsonValue, path: &str, kind: Kind) {
+    for (i, member) in value.members().enumerate() {
+      if !kind.matches(member) {
+        self.error(&index_path(path, i), format!("expected {}", kind.name()));
+      }
+    }
+  }
+
+  fn check_unique_names<'a>(&mut self, values: impl Iterator<Item = &'a JsonValue>, path: &str, kind: &str) {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for (i, value) in values.enumerate() {
+      let Some(name) = value["name"].as_str() else { continue };
+      if !seen.insert(name) {
+        self.error(&key_path(&index_path(path, i), "name"), format!("duplicate {kind} name \"{name}\""));
+      }
+    }
+  }
+
+  //Snapshot file names only keep characters that are safe everywhere, so tests like "get a/b" and "get a b" would share a file.
+  //They are compared lowercased since some file systems arent case sensitive
+  fn check_unique_snapshot_files(&mut self, test_chains: &JsonValue) {
+    let mut seen: HashMap<String, (&str, &str)> = HashMap::new();
+    for (i, test_chain) in test_chains.members().enumerate() {
+      let Some(test_chain_name) = test_chain["name"].as_str() else { continue };
+      for (j, test) in test_chain["tests"].members().enumerate() {
+        let snapshot = &test["expected_outcome"]["body_matches_snapshot"];
+        let Some(test_name) = test["name"].as_str() else { continue };
+        if !snapshot.is_object() && snapshot.as_bool() != Some(true) {
+          continue;
+        }
+        let file = snapshot::path(Path::new(""), test_chain_name, test_name).display().to_string();
+        match seen.get(&file.to_lowercase()) {
+          //identical names are already reported as duplicates
+          Some(&(other_chain, other_test)) if (other_chain, other_test) != (test_chain_name, test_name) => {
+            self.error(&key_path(&index_path(&key_path(&index_path("$.tests", i), "tests"), j), "name"), format!("snapshot file {file} is already used by test \"{other_test}\" of test chain \"{other_chain}\", rename one of them"));
+          },
+          Some(_) => {},
+          None => { seen.insert(file.to_lowercase(), (test_chain_name, test_name)); },
+        }
+      }
+    }
+  }
+
+  //Any method that is a valid HTTP token can be sent, except for CONNECT which opens a tunnel instead of requesting a resource
+  fn check_method(&mut self, value: &JsonValue, path: &str) {
+    if !self.required(value, path, "method", Kind::String) {
+      return;
+    }
+    let method = value["method"].as_str().unwrap_or_default();
+    let is_token = !method.is_empty() && method.chars().all(|x| x.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(x));
+
+    if !is_token {
+      self.error(&key_path(path, "method"), format!("\"{method}\" is not a valid HTTP method"));
+    } else if method.eq_ignore_ascii_case("CONNECT") {
+      self.error(&key_path(path, "method"), String::from("unsupported method \"CONNECT\""));
+    } else if method != method.to_uppercase() && STANDARD_METHODS.contains(&method.to_uppercase().as_str()) {
+      self.error(&key_path(path, "method"), format!("methods are case sensitive, did you mean \"{}\"?", method.to_uppercase()));
+    }
+  }
+
+  fn validate_root(&mut self, input: &JsonValue) {
+    if !input.is_object() {
+      self.error("$", format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(input, "$", &["configs", "tasks", "tests"]);
+
+    if self.required(input, "$", "configs", Kind::Array) {
+      for (i, config) in input["configs"].members().enumerate() {
+        self.validate_config(config, &index_path("$.configs", i));
+      }
+      self.check_unique_names(input["configs"].members(), "$.configs", "config");
+    }
+
+    if self.optional(input, "$", "tasks", Kind::Object) {
+      for (name, task) in input["tasks"].entries() {
+        self.validate_task(task, &key_path("$.tasks", name));
+      }
+    }
+
+    if self.required(input, "$", "tests", Kind::Array) {
+      for (i, test_chain) in input["tests"].members().enumerate() {
+        self.validate_test_chain(test_chain, &index_path("$.tests", i));
+      }
+      self.check_unique_names(input["tests"].members(), "$.tests", "test chain");
+      self.check_unique_snapshot_files(&input["tests"]);
+    }
+  }
+
+  fn validate_config(&mut self, config: &JsonValue, path: &str) {
+    if !config.is_object() {
+      self.error(path, format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(config, path, &["name", "description", "setup", "cleanup", "api_hostname", "timeout_ms"]);
+    self.optional_request_timeout(config, path);
+    self.required(config, path, "name", Kind::String);
+    self.optional(config, path, "description", Kind::String);
+    self.required(config, path, "api_hostname", Kind::String);
+
+    if self.optional(config, path, "setup", Kind::Object) {
+      let setup_path = key_path(path, "setup");
+      self.check_keys(&config["setup"], &setup_path, &["cmd", "background_cmd", "log_file", "finished_condition"]);
+      for key in ["cmd", "background_cmd", "log_file"] {
+        self.optional(&config["setup"], &setup_path, key, Kind::String);
+      }
+
+      if self.optional(&config["setup"], &setup_path, "finished_condition", Kind::Object) {
+        let finished_condition_path = key_path(&setup_path, "finished_condition");
+        let finished_condition = &config["setup"]["finished_condition"];
+        self.check_keys(finished_condition, &finished_condition_path, &[&READINESS_CONDITIONS[..], &["timeout_ms", "interval_ms", "backoff", "max_interval_ms"]].concat());
+        self.validate_readiness_conditions(finished_condition, &finished_condition_path);
+        self.optional(finished_condition, &finished_condition_path, "backoff", Kind::Bool);
+        for key in ["timeout_ms", "max_interval_ms"] {
+          self.optional_milliseconds(finished_condition, &finished_condition_path, key);
+        }
+        //checking without a pause in between would keep a whole core busy until the setup is finished
+        if self.optional(finished_condition, &finished_condition_path, "interval_ms", Kind::Integer) && finished_condition["interval_ms"].as_f64().unwrap_or_default() < 1.0 {
+          self.error(&key_path(&finished_condition_path, "interval_ms"), String::from("expected an interval in milliseconds of at least 1"));
+        }
+      }
+    }
+
+    if self.optional(config, path, "cleanup", Kind::Object) {
+      let cleanup_path = key_path(path, "cleanup");
+      self.check_keys(&config["cleanup"], &cleanup_path, &["cmd"]);
+      self.optional(&config["cleanup"], &cleanup_path, "cmd", Kind::String);
+    }
+  }
+
+  //Checks the condition keys of a finished_condition or of a group inside of all and any, unknown keys are checked by the caller
+  fn validate_readiness_conditions(&mut self, value: &JsonValue, path: &str) {
+    for key in ["endpoint_reachable", "command_succeeds", "file_exists"] {
+      self.optional(value, path, key, Kind::String);
+    }
+    if self.optional(value, path, "tcp_port_open", Kind::String) {
+      //{{worker_port}} only gets its value once a worker runs the setup
+      let address = variables::substitute(value["tcp_port_open"].as_str().unwrap_or_default(), &variables::worker_variables(0, 0));
+      if !address.chars().all(|x| x.is_ascii_digit()) && !address.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()) {
+        self.error(&key_path(path, "tcp_port_open"), format!("expected a port or host:port, got \"{address}\""));
+      }
+    }
+    if self.optional(value, path, "log_line_matches", Kind::String) {
+      if let Err(e) = regex_lite::Regex::new(value["log_line_matches"].as_str().unwrap_or_default()) {
+        self.error(&key_path(path, "log_line_matches"), format!("invalid regular expression: {e}"));
+      }
+    }
+    self.optional_milliseconds(value, path, "delay_ms");
+
+    if self.optional(value, path, "endpoint_status", Kind::Object) {
+      let endpoint_status_path = key_path(path, "endpoint_status");
+      self.check_keys(&value["endpoint_status"], &endpoint_status_path, &["endpoint", "status_code"]);
+      self.required(&value["endpoint_status"], &endpoint_status_path, "endpoint", Kind::String);
+      if self.required(&value["endpoint_status"], &endpoint_status_path, "status_code", Kind::Integer) {
+        self.check_status_code(&value["endpoint_status"], &endpoint_status_path, "status_code");
+      }
+    }
+    if self.optional(value, path, "endpoint_body_contains", Kind::Object) {
+      let endpoint_body_contains_path = key_path(path, "endpoint_body_contains");
+      self.check_keys(&value["endpoint_body_contains"], &endpoint_body_contains_path, &["endpoint", "text"]);
+      self.required(&value["endpoint_body_contains"], &endpoint_body_contains_path, "endpoint", Kind::String);
+      self.required(&value["endpoint_body_contains"], &endpoint_body_contains_path, "text", Kind::String);
+    }
+
+    for key in ["all", "any"] {
+      if !self.optional(value, path, key, Kind::Array) {
+        continue;
+      }
+      for (i, group) in value[key].members().enumerate() {
+        let group_path = index_path(&key_path(path, key), i);
+        if !group.is_object() {
+          self.error(&group_path, format!("expected {}", Kind::Object.name()));
+          continue;
+        }
+        self.check_keys(group, &group_path, &READINESS_CONDITIONS);
+        self.validate_readiness_conditions(group, &group_path);
+      }
+    }
+  }
+
+  fn validate_task(&mut self, task: &JsonValue, path: &str) {
+    if !task.is_object() {
+      self.error(path, format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(task, path, &["endpoint", "method", "body", "headers", "capture", "timeout_ms"]);
+    self.optional_request_timeout(task, path);
+    self.required(task, path, "endpoint", Kind::String);
+    self.check_method(task, path);
+    self.validate_task_reference_map(task, path, "headers", None);
+    self.validate_capture(task, path);
+  }
+
+  //Variable names are used as {{name}}, so they cant contain braces or whitespace
+  fn validate_capture(&mut self, value: &JsonValue, path: &str) {
+    if !self.optional(value, path, "capture", Kind::Object) {
+      return;
+    }
+    let capture_path = key_path(path, "capture");
+    self.check_object_values(&value["capture"], &capture_path, Kind::String);
+
+    for (name, source) in value["capture"].entries() {
+      if name.is_empty() || name.chars().any(|x| x.is_whitespace() || x == '{' || x == '}') {
+        self.error(&key_path(&capture_path, name), format!("\"{name}\" is not a valid variable name"));
+      }
+      let Some(source) = source.as_str() else { continue };
+      if !variables::is_valid_source(source) {
+        self.error(&key_path(&capture_path, name), format!("invalid source \"{source}\", expected a path like $.id, header:Name, cookie:name or status"));
+      }
+    }
+  }
+
+  fn validate_test_chain(&mut self, test_chain: &JsonValue, path: &str) {
+    if !test_chain.is_object() {
+      self.error(path, format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(test_chain, path, &["name", "defaults", "stop_on_failure", "tags", "tests"]);
+    self.required(test_chain, path, "name", Kind::String);
+    self.optional(test_chain, path, "stop_on_failure", Kind::Bool);
+    self.validate_tags(test_chain, path);
+
+    if self.optional(test_chain, path, "defaults", Kind::Object) {
+      let defaults_path = key_path(path, "defaults");
+      self.check_keys(&test_chain["defaults"], &defaults_path, &["cookies", "headers", "before", "tags", "timeout_ms", "retry"]);
+      self.optional_request_timeout(&test_chain["defaults"], &defaults_path);
+      self.validate_retry(&test_chain["defaults"], &defaults_path);
+      self.validate_before(&test_chain["defaults"], &defaults_path);
+      self.validate_tags(&test_chain["defaults"], &defaults_path);
+      self.validate_task_reference_map(&test_chain["defaults"], &defaults_path, "cookies", Some(&test_chain["defaults"]["before"]));
+      self.validate_task_reference_map(&test_chain["defaults"], &defaults_path, "headers", Some(&test_chain["defaults"]["before"]));
+    }
+
+    if self.required(test_chain, path, "tests", Kind::Array) {
+      for (i, test) in test_chain["tests"].members().enumerate() {
+        self.validate_test(test, &index_path(&key_path(path, "tests"), i), &test_chain["defaults"]);
+      }
+      self.check_unique_names(test_chain["tests"].members(), &key_path(path, "tests"), "test");
+    }
+  }
+
+  fn validate_test(&mut self, test: &JsonValue, path: &str, defaults: &JsonValue) {
+    if !test.is_object() {
+      self.error(path, format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(test, path, &["name", "endpoint", "method", "body", "cookies", "headers", "before", "capture", "expected_outcome", "skip", "tags", "timeout_ms", "retry"]);
+    self.optional_request_timeout(test, path);
+    self.required(test, path, "name", Kind::String);
+    self.required(test, path, "endpoint", Kind::String);
+    self.check_method(test, path);
+    self.optional(test, path, "skip", Kind::Bool);
+    self.validate_tags(test, path);
+    self.validate_before(test, path);
+
+    let before = if test["before"].is_null() { &defaults["before"] } else { &test["before"] };
+    self.validate_task_reference_map(test, path, "cookies", Some(before));
+    self.validate_task_reference_map(test, path, "headers", Some(before));
+    self.validate_capture(test, path);
+
+    if self.optional(test, path, "expected_outcome", Kind::Object) {
+      self.validate_outcome(&test["expected_outcome"], &key_path(path, "expected_outcome"));
+    }
+    self.validate_retry(test, path);
+  }
+
+  //Checks an expected_outcome or the until of a retry, which can contain the same keys
+  fn validate_outcome(&mut self, outcome: &JsonValue, path: &str) {
+    self.check_keys(outcome, path, &["status_code_equals", "body_equals", "body_contains", "headers_equal", "headers_contain", "headers_absent", "assertions", "max_duration_ms", "body_matches_snapshot", "body_matches_schema"]);
+    self.optional_milliseconds(outcome, path, "max_duration_ms");
+    self.validate_snapshot(outcome, path);
+    //a string is the path of a schema file, those are loaded and checked before the validation
+    if !outcome["body_matches_schema"].is_null() && !outcome["body_matches_schema"].is_string() {
+      let schema_path = key_path(path, "body_matches_schema");
+      if outcome["body_matches_schema"].is_object() {
+        self.validate_schema(&outcome["body_matches_schema"], &schema_path, &outcome["body_matches_schema"]);
+      } else {
+        self.error(&schema_path, format!("expected {} or the path of a schema file", Kind::Object.name()));
+      }
+    }
+    for key in ["body_equals", "body_contains"] {
+      self.check_matchers(&outcome[key], &key_path(path, key));
+    }
+    if self.optional(outcome, path, "status_code_equals", Kind::Integer) {
+      self.check_status_code(outcome, path, "status_code_equals");
+    }
+    for key in ["headers_equal", "headers_contain"] {
+      if self.optional(outcome, path, key, Kind::Object) {
+        self.check_object_values(&outcome[key], &key_path(path, key), Kind::String);
+      }
+    }
+    if self.optional(outcome, path, "headers_absent", Kind::Array) {
+      self.check_array_members(&outcome["headers_absent"], &key_path(path, "headers_absent"), Kind::String);
+    }
+    if self.optional(outcome, path, "assertions", Kind::Array) {
+      let assertions_path = key_path(path, "assertions");
+      for (i, assertion) in outcome["assertions"].members().enumerate() {
+        self.validate_assertion(assertion, &index_path(&assertions_path, i));
+      }
+    }
+  }
+
+  fn validate_retry(&mut self, value: &JsonValue, path: &str) {
+    if !self.optional(value, path, "retry", Kind::Object) {
+      return;
+    }
+    let retry_path = key_path(path, "retry");
+    self.check_keys(&value["retry"], &retry_path, &["attempts", "delay_ms", "until"]);
+    if self.required(&value["retry"], &retry_path, "attempts", Kind::Integer) && value["retry"]["attempts"].as_f64().unwrap_or_default() < 1.0 {
+      self.error(&key_path(&retry_path, "attempts"), String::from("expected at least 1 attempt"));
+    }
+    self.optional_milliseconds(&value["retry"], &retry_path, "delay_ms");
+    if self.optional(&value["retry"], &retry_path, "until", Kind::Object) {
+      self.validate_outcome(&value["retry"]["until"], &key_path(&retry_path, "until"));
+    }
+  }
+
+  //A typo in a matcher like %%%ANY_NUMBR%%% would otherwise only be compared as text and fail every run
+  fn check_matchers(&mut self, value: &JsonValue, path: &str) {
+    if let Some(Err(e)) = value.as_str().and_then(matcher::parse) {
+      self.error(path, e);
+    }
+    for (i, member) in value.members().enumerate() {
+      self.check_matchers(member, &index_path(path, i));
+    }
+    for (key, member) in value.entries() {
+      self.check_matchers(member, &key_path(path, key));
+    }
+  }
+
+  //root is the whole schema, $ref can point anywhere inside it
+  fn validate_schema(&mut self, schema: &JsonValue, path: &str, root: &JsonValue) {
+    if schema.is_boolean() {
+      return;
+    }
+    if !schema.is_object() {
+      self.error(path, format!("expected {} or {}", Kind::Bool.name(), Kind::Object.name()));
+      return;
+    }
+    self.check_keys(schema, path, &schema::KEYWORDS);
+
+    let types: Vec<&JsonValue> = if schema["type"].is_array() { schema["type"].members().collect() } else if schema["type"].is_null() { Vec::new() } else { vec![&schema["type"]] };
+    if types.iter().any(|x| !x.as_str().is_some_and(|x| assertion::JSON_TYPES.contains(&x))) {
+      self.error(&key_path(path, "type"), format!("expected one of {} or an array of them", assertion::JSON_TYPES.join(", ")));
+    }
+    for key in ["properties", "$defs", "definitions"] {
+      if self.optional(schema, path, key, Kind::Object) {
+        for (name, subschema) in schema[key].entries() {
+          self.validate_schema(subschema, &key_path(&key_path(path, key), name), root);
+        }
+      }
+    }
+    for key in ["items", "additionalProperties"] {
+      if !schema[key].is_null() {
+        self.validate_schema(&schema[key], &key_path(path, key), root);
+      }
+    }
+    if self.optional(schema, path, "required", Kind::Array) {
+      self.check_array_members(&schema["required"], &key_path(path, "required"), Kind::String);
+    }
+    self.optional(schema, path, "enum", Kind::Array);
+    if self.optional(schema, path, "pattern", Kind::String) {
+      if let Err(e) = regex_lite::Regex::new(schema["pattern"].as_str().unwrap_or_default()) {
+        self.error(&key_path(path, "pattern"), format!("invalid regular expression: {e}"));
+      }
+    }
+    for key in ["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"] {
+      self.optional(schema, path, key, Kind::Number);
+    }
+    for key in ["minLength", "maxLength", "minItems", "maxItems"] {
+      if self.optional(schema, path, key, Kind::Integer) && schema[key].as_f64().unwrap_or_default() < 0.0 {
+        self.error(&key_path(path, key), String::from("expected a number that isnt negative"));
+      }
+    }
+    if self.optional(schema, path, "$ref", Kind::String) && schema::resolve_ref(root, schema["$ref"].as_str().unwrap_or_default()).is_none() {
+      self.error(&key_path(path, "$ref"), format!("{} doesnt point to anything in the schema, only references within it like #/$defs/name are supported", schema["$ref"]));
+    }
+  }
+
+  //true or an object with the paths to redact
+  fn validate_snapshot(&mut self, outcome: &JsonValue, path: &str) {
+    let snapshot = &outcome["body_matches_snapshot"];
+    if snapshot.is_null() || snapshot.is_boolean() {
+      return;
+    }
+    let snapshot_path = key_path(path, "body_matches_snapshot");
+    if !snapshot.is_object() {
+      self.error(&snapshot_path, format!("expected {} or {}", Kind::Bool.name(), Kind::Object.name()));
+      return;
+    }
+    self.check_keys(snapshot, &snapshot_path, &["redact"]);
+    if !self.required(snapshot, &snapshot_path, "redact", Kind::Array) {
+      return;
+    }
+    let redact_path = key_path(&snapshot_path, "redact");
+    for (i, redacted) in snapshot["redact"].members().enumerate() {
+      if !redacted.is_string() {
+        self.error(&index_path(&redact_path, i), format!("expected {}", Kind::String.name()));
+      } else if let Err(e) = json_path::parse(redacted.as_str().unwrap_or_default()) {
+        self.error(&index_path(&redact_path, i), e);
+      }
+    }
+  }
+
+  fn validate_assertion(&mut self, assertion: &JsonValue, path: &str) {
+    if !assertion.is_object() {
+      self.error(path, format!("expected {}", Kind::Object.name()));
+      return;
+    }
+    self.check_keys(assertion, path, &["path", "operator", "value"]);
+    if self.required(assertion, path, "path", Kind::String) {
+      match json_path::parse(assertion["path"].as_str().unwrap_or_default()) {
+        Err(e) => self.error(&key_path(path, "path"), e),
+        Ok(segments) if segments.contains(&json_path::Segment::Wildcard) => self.error(&key_path(path, "path"), String::from("[*] can only be used in redacted paths of body_matches_snapshot")),
+        Ok(_) => (),
+      }
+    }
+    if !self.required(assertion, path, "operator", Kind::String) {
+      return;
+    }
+    let operator_name = assertion["operator"].as_str().unwrap_or_default();
+    let Some(operator) = AssertionOperator::parse(operator_name) else {
+      self.error(&key_path(path, "operator"), format!("unknown operator \"{operator_name}\""));
+      return;
+    };
+
+    let value = &assertion["value"];
+    if !operator.needs_value() {
+      if !value.is_null() {
+        self.error(&key_path(path, "value"), format!("operator \"{operator_name}\" doesnt take a value"));
+      }
+      return;
+    }
+    if value.is_null() && !assertion.has_key("value") {
+      self.error(path, format!("missing required key \"value\" for operator \"{operator_name}\""));
+      return;
+    }
+
+    self.check_matchers(value, &key_path(path, "value"));
+    match operator {
+      AssertionOperator::Gt | AssertionOperator::Lt if !value.is_number() && !value.is_string() => {
+        self.error(&key_path(path, "value"), format!("operator \"{operator_name}\" expects a number or a string"));
+      },
+      AssertionOperator::Matches if !value.is_string() => {
+        self.error(&key_path(path, "value"), format!("expected {}", Kind::String.name()));
+      },
+      AssertionOperator::Matches if regex_lite::Regex::new(value.as_str().unwrap_or_default()).is_err() => {
+        self.error(&key_path(path, "value"), format!("invalid regular expression: {}", regex_lite::Regex::new(value.as_str().unwrap_or_default()).unwrap_err()));
+      },
+      AssertionOperator::TypeIs if !value.as_str().is_some_and(|x| assertion::JSON_TYPES.contains(&x)) => {
+        self.error(&key_path(path, "value"), format!("expected one of {}", assertion::JSON_TYPES.join(", ")));
+      },
+      _ => (),
+    }
+  }
+
+  fn validate_tags(&mut self, value: &JsonValue, path: &str) {
+    if !self.optional(value, path, "tags", Kind::Array) {
+      return;
+    }
+    let tags_path = key_path(path, "tags");
+    self.check_array_members(&value["tags"], &tags_path, Kind::String);
+
+    for (i, tag) in value["tags"].members().enumerate() {
+      let Some(tag) = tag.as_str() else { continue };
+      if !tags::is_valid_tag(tag) {
+        self.error(&index_path(&tags_path, i), format!("\"{tag}\" is not a valid tag, tags can only contain letters, digits, _, -, : and . and cant be and, or or not"));
+      }
+    }
+  }
+
+  fn validate_before(&mut self, value: &JsonValue, path: &str) {
+    if !self.optional(value, path, "before", Kind::Array) {
+      return;
+    }
+    let before_path = key_path(path, "before");
+    self.check_array_members(&value["before"], &before_path, Kind::String);
+
+    for (i, task_name) in value["before"].members().enumerate() {
+      let Some(task_name) = task_name.as_str() else { continue };
+      if !self.task_names.contains(task_name) {
+        self.error(&index_path(&before_path, i), format!("task \"{task_name}\" is not defined in tasks"));
+      }
+    }
+  }
+
+  //Checks a map of strings like cookies or headers whose values can reference before tasks with $task_name.key
+  //before is the list of tasks that actually runs for this value, values can only reference the results of those
+  //Tasks dont run before tasks themselves, so they pass None
+  fn validate_task_reference_map(&mut self, value: &JsonValue, path: &str, key: &str, before: Option<&JsonValue>) {
+    if !self.optional(value, path, key, Kind::Object) {
+      return;
+    }
+    let map_path = key_path(path, key);
+    self.check_object_values(&value[key], &map_path, Kind::String);
+
+    //a $ that doesnt reference a defined task is sent as it is, like in $5.00
+    let task_names: Vec<String> = self.task_names.iter().cloned().collect();
+    let task_names: Vec<&str> = task_names.iter().map(String::as_str).collect();
+    for (entry_name, entry_value) in value[key].entries() {
+      let Some(entry_value) = entry_value.as_str() else { continue };
+
+      for (_, task_name, _) in http_request::find_task_references(entry_value, &task_names) {
+        if before.is_none() {
+          self.error(&key_path(&map_path, entry_name), format!("tasks cant reference the result of task \"{task_name}\""));
+        } else if !before.unwrap().members().any(|x| x.as_str() == Some(task_name.as_str())) {
+          self.error(&key_path(&map_path, entry_name), format!("task \"{task_name}\" is not run in before"));
+        }
+      }
+    }
+  }
+}
+
+//Maps every path in the json document to the line and column it starts at
+//Members of objects point to their key, as thats where a reader would look for them
+fn locate_paths(source: &str) -> HashMap<String, (usize, usize)> {
+  let mut scanner = Scanner {
+    chars: source.chars().peekable(),
+    line: 1,
+    column: 1,
+    locations: HashMap::new(),
+  };
+
+  scanner.skip_whitespace();
+  scanner.locations.insert(String::from("$"), (scanner.line, scanner.column));
+  scanner.value("$");
+
+  return scanner.locations;
+}
+
+struct Scanner<'a> {
+  chars: std::iter::Peekable<std::str::Chars<'a>>,
+  line: usize,
+  column: usize,
+  locations: HashMap<String, (usize, usize)>,
+}
+
+impl Scanner<'_> {
+  fn bump(&mut self) -> Option<char> {
+    let next = self.chars.next();
+    if next == Some('\n') {
+      self.line += 1;
+      self.column = 1;
+    } else if next.is_some() {
+      self.column += 1;
+    }
+    return next;
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.chars.peek().is_some_and(|x| x.is_whitespace()) {
+      self.bump();
+    }
+  }
+
+  fn value(&mut self, path: &str) {
+    self.skip_whitespace();
+    match self.chars.peek() {
+      Some('{') => {
+        self.bump();
+        loop {
+          self.skip_whitespace();
+          if self.chars.peek() != Some(&'"') {
+            self.bump();
+            break;
+          }
+          let location = (self.line, self.column);
+          let key = self.string();
+          let child_path = key_path(path, &key);
+          self.locations.insert(child_path.clone(), location);
+          self.skip_whitespace();
+          self.bump();
+          self.value(&child_path);
+          self.skip_whitespace();
+          if self.bump() != Some(',') {
+            break;
+          }
+        }
+      },
+      Some('[') => {
+        self.bump();
+        let mut index = 0;
+        loop {
+          self.skip_whitespace();
+          if self.chars.peek() == Some(&']') || self.chars.peek().is_none() {
+            self.bump();
+            break;
+          }
+          let child_path = index_path(path, index);
+          self.locations.insert(child_path.clone(), (self.line, self.column));
+          self.value(&child_path);
+          index += 1;
+          self.skip_whitespace();
+          if self.bump() != Some(',') {
+            break;
+          }
+        }
+      },
+      Some('"') => {
+        self.string();
+      },
+      _ => {
+        while self.chars.peek().is_some_and(|x| !matches!(x, ',' | '}' | ']') && !x.is_whitespace()) {
+          self.bump();
+        }
+      },
+    }
+  }
+
+  fn string(&mut self) -> String {
+    let mut output = String::new();
+    self.bump();
+    while let Some(next) = self.bump() {
+      match next {
+        '"' => break,
+        '\\' => match self.bump() {
+          Some('n') => output.push('\n'),
+          Some('t') => output.push('\t'),
+          Some('r') => output.push('\r'),
+          Some('b') => output.push('\u{8}'),
+          Some('f') => output.push('\u{c}'),
+          Some('u') => {
+            let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+            output.push(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+          },
+          Some(x) => output.push(x),
+          None => break,
+        },
+        x => output.push(x),
+      }
+    }
+    return output;
+  }
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+  use std::fs;
+
+  fn validate_str(source: &str) -> Vec<ValidationError> {
+    return validate(&jzon::parse(source).unwrap(), source, Path::new("./test")).1;
+  }
+
+  #[test]
+  fn sample_configs_are_valid() {
+    for file in ["./test/sample.json", "./test/sample_parsed.json", "./test/init.json"] {
+      let source = fs::read_to_string(file).unwrap();
+      assert_eq!(validate_str(&source), Vec::new(), "{file}");
+    }
+  }
+
+  #[test]
+  fn reports_missing_keys_and_wrong_types() {
+    let res = validate_str(r#"{"configs":[{"name":1}],"tests":[]}"#);
+
+    assert_eq!(res, vec![
+      ValidationError { path: "$.configs[0].name".to_string(), message: "expected a string".to_string(), location: Some((1, 14)) },
+      ValidationError { path: "$.configs[0]".to_string(), message: "missing required key \"api_hostname\"".to_string(), location: Some((1, 13)) },
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","stop_on_failure":"yes","tags":["smoke",1,"not","two words"],"tests":[]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].stop_on_failure: expected a boolean",
+      "$.tests[0].tags[1]: expected a string",
+      "$.tests[0].tags[2]: \"not\" is not a valid tag, tags can only contain letters, digits, _, -, : and . and cant be and, or or not",
+      "$.tests[0].tags[3]: \"two words\" is not a valid tag, tags can only contain letters, digits, _, -, : and . and cant be and, or or not",
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","defaults":{"timeout_ms":0},"tests":[{"name":"b","endpoint":"/","method":"GET","timeout_ms":"5s","expected_outcome":{}}]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].defaults.timeout_ms: expected a timeout in milliseconds greater than 0",
+      "$.tests[0].tests[0].timeout_ms: expected an integer",
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","defaults":{"retry":{"attempts":0,"delay":5}},"tests":[{"name":"b","endpoint":"/","method":"GET","retry":{"delay_ms":100,"until":{"status_code_equals":"200"}}}]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].defaults.retry.delay: unknown key \"delay\", expected one of attempts, delay_ms, until",
+      "$.tests[0].defaults.retry.attempts: expected at least 1 attempt",
+      "$.tests[0].tests[0].retry: missing required key \"attempts\"",
+      "$.tests[0].tests[0].retry.until.status_code_equals: expected an integer",
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","tests":[{"name":"b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":{"redact":["$[*].created_at","$.x[",1]},"assertions":[{"path":"$[*].id","operator":"exists"}]}},{"name":"c","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":"yes"}}]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[0].expected_outcome.body_matches_snapshot.redact[1]: path \"$.x[\" contains an unterminated index",
+      "$.tests[0].tests[0].expected_outcome.body_matches_snapshot.redact[2]: expected a string",
+      "$.tests[0].tests[0].expected_outcome.assertions[0].path: [*] can only be used in redacted paths of body_matches_snapshot",
+      "$.tests[0].tests[1].expected_outcome.body_matches_snapshot: expected a boolean or an object",
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","tests":[{"name":"get a/b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":true}},{"name":"get a b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":{"redact":[]}}},{"name":"Get a b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":false}}]},{"name":"A","tests":[{"name":"get a_b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_snapshot":true}}]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[1].name: snapshot file a/get_a_b.json is already used by test \"get a/b\" of test chain \"a\", rename one of them",
+      "$.tests[1].tests[0].name: snapshot file A/get_a_b.json is already used by test \"get a/b\" of test chain \"a\", rename one of them",
+    ]);
+  }
+
+  #[test]
+  fn reports_unknown_keys_with_suggestion() {
+    let res = validate_str("{\n\"configs\":[],\n\"tests\":[{\"name\":\"a\",\"tests\":[{\"name\":\"b\",\"endpoint\":\"/\",\"method\":\"GET\",\n\"expected_outcom\":{}}]}]}");
+
+    assert_eq!(res, vec![
+      ValidationError { path: "$.tests[0].tests[0].expected_outcom".to_string(), message: "unknown key \"expected_outcom\", did you mean \"expected_outcome\"?".to_string(), location: Some((4, 1)) },
+    ]);
+  }
+
+  #[test]
+  fn reports_undefined_tasks_duplicate_names_and_methods() {
+    let res = validate_str(r#"{"configs":[],"tasks":{"login":{"endpoint":"/login","method":"POST"}},"tests":[{"name":"a","tests":[
+      {"name":"b","endpoint":"/","method":"FE TCH","before":["logn"]},
+      {"name":"b","endpoint":"/","method":"GET","cookies":{"token":"$login.token"}},
+      {"name":"c","endpoint":"/","method":"patch"},
+      {"name":"d","endpoint":"/","method":"PROPFIND"}
+    ]}]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[0].method: \"FE TCH\" is not a valid HTTP method",
+      "$.tests[0].tests[0].before[0]: task \"logn\" is not defined in tasks",
+      "$.tests[0].tests[1].cookies.token: task \"login\" is not run in before",
+      "$.tests[0].tests[2].method: methods are case sensitive, did you mean \"PATCH\"?",
+      "$.tests[0].tests[1].name: duplicate test name \"b\"",
+    ]);
+
+    let res = validate_str(r#"{"configs":[],"tasks":{"login as admin":{"endpoint":"/login","method":"POST"}},"tests":[{"name":"a","tests":[
+      {"name":"b","endpoint":"/","method":"GET","cookies":{"token":"$login as admin.token"},"headers":{"X-Price":"$5.00","X-Other":"$other.key"}},
+      {"name":"c","endpoint":"/","method":"GET","before":["login as admin"],"cookies":{"token":"$login as admin.token"}}
+    ]}]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[0].cookies.token: task \"login as admin\" is not run in before",
+    ]);
+  }
+
+  #[test]
+  fn reports_invalid_finished_condition() {
+    let res = validate_str(r#"{"configs":[{"name":"a","api_hostname":"http://localhost","setup":{"finished_condition":{"endpoint_reachable":"/","timeout_ms":-1,"interval_ms":"100","backoff":1}}}],"tests":[]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.configs[0].setup.finished_condition.backoff: expected a boolean",
+      "$.configs[0].setup.finished_condition.timeout_ms: expected a duration in milliseconds that isnt negative",
+      "$.configs[0].setup.finished_condition.interval_ms: expected an integer",
+    ]);
+
+    let res = validate_str(r#"{"configs":[{"name":"a","api_hostname":"http://localhost","setup":{"finished_condition":{"endpoint_reachable":"/","interval_ms":0}}}],"tests":[]}"#);
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.configs[0].setup.finished_condition.interval_ms: expected an interval in milliseconds of at least 1",
+    ]);
+  }
+
+  #[test]
+  fn reports_invalid_readiness_conditions() {
+    let res = validate_str(r#"{"configs":[{"name":"a","api_hostname":"http://localhost","setup":{"finished_condition":{
+      "endpoint_status":{"endpoint":"/health","status_code":700},
+      "tcp_port_open":"localhost",
+      "any":[{"log_line_matches":"(ready","delay_ms":10},{"command_succeeds":"pg_isready","timeout_ms":5},"file_exists"]
+    }}}],"tests":[]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.configs[0].setup.finished_condition.tcp_port_open: expected a port or host:port, got \"localhost\"",
+      "$.configs[0].setup.finished_condition.endpoint_status.status_code: 700 is not a valid HTTP status code",
+      "$.configs[0].setup.finished_condition.any[0].log_line_matches: invalid regular expression: found open group without closing ')'",
+      "$.configs[0].setup.finished_condition.any[1].timeout_ms: unknown key \"timeout_ms\", expected one of endpoint_reachable, endpoint_status, endpoint_body_contains, tcp_port_open, command_succeeds, file_exists, log_line_matches, delay_ms, all, any",
+      "$.configs[0].setup.finished_condition.any[2]: expected an object",
+    ]);
+
+    assert_eq!(validate_str(r#"{"configs":[{"name":"a","api_hostname":"http://localhost","setup":{"finished_condition":{"tcp_port_open":"localhost:{{worker_port}}"}}}],"tests":[]}"#), Vec::new());
+  }
+
+  #[test]
+  fn reports_missing_environment_variables() {
+    let res = validate_str(r#"{"configs":[{"name":"a","api_hostname":"${TREST_UNSET_HOSTNAME}","setup":{"cmd":"${TREST_UNSET_CMD:-true}"}}],"tests":[]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.configs[0].api_hostname: environment variable TREST_UNSET_HOSTNAME is not set and has no default",
+    ]);
+  }
+
+  #[test]
+  fn validates_interpolated_values() {
+    let res = validate_str(r#"{"configs":[{"name":"a","api_hostname":"http://localhost","setup":{"finished_condition":{"tcp_port_open":"${TREST_UNSET_PORT:-4000}"}}}],"tests":[{"name":"a","tests":[
+      {"name":"b","endpoint":"/","method":"${TREST_UNSET_METHOD:-GET}","tags":["${TREST_UNSET_TAG:-smoke}"]}
+    ]}]}"#);
+
+    assert_eq!(res, Vec::new());
+  }
+
+  #[test]
+  fn reports_invalid_captures() {
+    let res = validate_str(r#"{"configs":[],"tasks":{"login":{"endpoint":"/login","method":"POST","capture":{"token":"$.token","bad name":"status"}}},"tests":[{"name":"a","tests":[
+      {"name":"b","endpoint":"/","method":"GET","capture":{"id":"$.items[0].id","location":"header:Location","session":"cookie:","other":"body"}}
+    ]}]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tasks.login.capture[\"bad name\"]: \"bad name\" is not a valid variable name",
+      "$.tests[0].tests[0].capture.session: invalid source \"cookie:\", expected a path like $.id, header:Name, cookie:name or status",
+      "$.tests[0].tests[0].capture.other: invalid source \"body\", expected a path like $.id, header:Name, cookie:name or status",
+    ]);
+  }
+
+  #[test]
+  fn reports_invalid_assertions() {
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","tests":[{"name":"b","endpoint":"/","method":"GET","expected_outcome":{"assertions":[
+      {"path":"$.items.length","operator":"equals","value":3},
+      {"path":"items","operator":"exists"},
+      {"path":"$.id","operator":"bigger","value":1},
+      {"path":"$.id","operator":"gt"},
+      {"path":"$.id","operator":"not_exists","value":1},
+      {"path":"$.name","operator":"matches","value":"(unclosed"},
+      {"path":"$.name","operator":"type_is","value":"text"}
+    ]}}]}]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[0].expected_outcome.assertions[1].path: path \"items\" has to start with $",
+      "$.tests[0].tests[0].expected_outcome.assertions[2].operator: unknown operator \"bigger\"",
+      "$.tests[0].tests[0].expected_outcome.assertions[3]: missing required key \"value\" for operator \"gt\"",
+      "$.tests[0].tests[0].expected_outcome.assertions[4].value: operator \"not_exists\" doesnt take a value",
+      "$.tests[0].tests[0].expected_outcome.assertions[5].value: invalid regular expression: found open group without closing ')'",
+      "$.tests[0].tests[0].expected_outcome.assertions[6].value: expected one of string, number, integer, boolean, array, object, null",
+    ]);
+  }
+
+  #[test]
+  fn reports_invalid_schemas() {
+    let res = validate_str(r##"{"configs":[],"tests":[{"name":"a","tests":[
+      {"name":"b","endpoint":"/","method":"GET","expected_outcome":{"body_matches_schema":{"type":"array","minItems":-1,"items":{"$ref":"#/$defs/item"},"$defs":{"item":{
+        "type":["object","map"],"requird":["id"],"properties":{"id":{"type":"integer","minimum":"0"},"name":{"pattern":"(a","oneOf":[]}},"additionalProperties":{"$ref":"#/$defs/missing"}
+      }}}}},
+      {"name":"c","endpoint":"/","method":"GET","expected_outcome":{"body_matches_schema":"schemas/item.json"}},
+      {"name":"d","endpoint":"/","method":"GET","expected_outcome":{"body_matches_schema":true}}
+    ]}]}"##);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[1].expected_outcome.body_matches_schema: failed to read schema file schemas/item.json: No such file or directory (os error 2)",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.requird: unknown key \"requird\", did you mean \"required\"?",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.type: expected one of string, number, integer, boolean, array, object, null or an array of them",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.properties.id.minimum: expected a number",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.properties.name.oneOf: unknown key \"oneOf\", expected one of $schema, $id, $comment, $defs, definitions, $ref, title, description, default, examples, type, properties, required, items, additionalProperties, enum, pattern, minimum, maximum, exclusiveMinimum, exclusiveMaximum, minLength, maxLength, minItems, maxItems, const",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.properties.name.pattern: invalid regular expression: found open group without closing ')'",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema[\"$defs\"].item.additionalProperties[\"$ref\"]: #/$defs/missing doesnt point to anything in the schema, only references within it like #/$defs/name are supported",
+      "$.tests[0].tests[0].expected_outcome.body_matches_schema.minItems: expected a number that isnt negative",
+      "$.tests[0].tests[2].expected_outcome.body_matches_schema: expected an object or the path of a schema file",
+    ]);
+  }
+
+  #[test]
+  fn reports_invalid_matchers() {
+    let res = validate_str(r#"{"configs":[],"tests":[{"name":"a","tests":[{"name":"b","endpoint":"/","method":"GET","expected_outcome":{
+      "body_equals":{"id":"%%%ANY_NUMBR%%%","items":[{"created_at":"%%%ISO8601%%%","score":"%%%BETWEEN(10,1)%%%"}],"ref":"%%%[[[c]]]...[[[id]]]%%%"},
+      "body_contains":{"name":"%%%REGEX(^[a-z%%%","kind":"%%%ONE_OF%%%","tag":"%%%ANY_STRING(x)%%%","other":"%%%LIKE(x)%%%"},
+      "assertions":[{"path":"$.id","operator":"equals","value":"%%%ANY_INTEGER%%%"},{"path":"$.email","operator":"equals","value":"%%%EMAIL"}]
+    }}]}]}"#);
+
+    assert_eq!(res.iter().map(|x| format!("{}: {}", x.path, x.message)).collect::<Vec<String>>(), vec![
+      "$.tests[0].tests[0].expected_outcome.body_equals.id: unknown matcher ANY_NUMBR, did you mean ANY_NUMBER?",
+      "$.tests[0].tests[0].expected_outcome.body_equals.items[0].score: matcher %%%BETWEEN(10,1)%%% expects a minimum and a maximum number like BETWEEN(1,100)",
+      "$.tests[0].tests[0].expected_outcome.body_contains.name: matcher %%%REGEX(^[a-z%%% is missing a closing )",
+      "$.tests[0].tests[0].expected_outcome.body_contains.kind: matcher ONE_OF needs arguments like ONE_OF(...)",
+      "$.tests[0].tests[0].expected_outcome.body_contains.tag: matcher ANY_STRING doesnt take arguments",
+      "$.tests[0].tests[0].expected_outcome.body_contains.other: unknown matcher LIKE, expected one of ANY, ANY_STRING, ANY_UUID, ANY_NUMBER, ANY_INTEGER, ANY_BOOL, ANY_ARRAY, ANY_OBJECT, NOT_NULL, ISO8601, EMAIL, REGEX, BETWEEN, ONE_OF",
+    ]);
+  }
+}
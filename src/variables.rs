@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use jzon::JsonValue;
+use linked_hash_map::LinkedHashMap;
+
+use crate::{json_path, Config, ReadinessCondition, Task, Test, TestOutcome};
+
+//Variables are captured from responses of tests and tasks and can be used as {{name}} by everything that runs after them in the same test chain
+pub type Variables = HashMap<String, JsonValue>;
+
+//The parts of a response that capture sources can read from
+pub struct CaptureResponse<'a> {
+  pub status_code: u16,
+  pub headers: &'a LinkedHashMap<String, String>,
+  pub cookies: &'a LinkedHashMap<String, String>,
+  pub body: &'a JsonValue,
+}
+
+//Stores the value of every source in captures under its variable name
+//Returns a message for every source that didnt have a value in the response, those variables are left unchanged
+pub fn capture(captures: &LinkedHashMap<String, String>, response: &CaptureResponse, variables: &mut Variables) -> Vec<String> {
+  let mut output: Vec<String> = Vec::new();
+
+  for (name, source) in captures {
+    match get_source_value(source, response) {
+      Some(value) => { variables.insert(name.clone(), value); },
+      None => output.push(format!("couldnt capture {name}, {source} had no value")),
+    }
+  }
+
+  return output;
+}
+
+//Sources are a path into the json body like $.items[0].id, header:Name, cookie:name or status
+fn get_source_value(source: &str, response: &CaptureResponse) -> Option<JsonValue> {
+  if source == "status" {
+    return Some(response.status_code.into());
+  }
+  if let Some(name) = source.strip_prefix("header:") {
+    return response.headers.get(&name.to_lowercase()).map(|x| x.as_str().into());
+  }
+  if let Some(name) = source.strip_prefix("cookie:") {
+    return response.cookies.get(name).map(|x| x.as_str().into());
+  }
+  return json_path::get(response.body, &json_path::parse(source).ok()?);
+}
+
+pub fn is_valid_source(source: &str) -> bool {
+  return source == "status"
+    || source.strip_prefix("header:").is_some_and(|x| !x.is_empty())
+    || source.strip_prefix("cookie:").is_some_and(|x| !x.is_empty())
+    || json_path::parse(source).is_ok();
+}
+
+//Replaces every {{name}} in value with the variable name, strings are inserted without quotes and everything else as json
+//Unknown variables are left as they are
+pub fn substitute(value: &str, variables: &Variables) -> String {
+  let mut output = String::new();
+  let mut rest = value;
+
+  while let Some(start) = rest.find("{{") {
+    let Some(length) = rest[start..].find("}}") else { break };
+    let name = rest[start + 2..start + length].trim();
+    output.push_str(&rest[..start]);
+    match variables.get(name) {
+      Some(variable) if variable.is_string() => output.push_str(variable.as_str().unwrap_or_default()),
+      Some(variable) => output.push_str(&variable.dump()),
+      None => output.push_str(&rest[start..start + length + 2]),
+    }
+    rest = &rest[start + length + 2..];
+  }
+
+  output.push_str(rest);
+  return output;
+}
+
+//Substitutes inside of all strings in a json value, strings that are nothing but a single "{{name}}" are replaced by the variable itself so numbers stay numbers
+pub fn substitute_json(value: &JsonValue, variables: &Variables) -> JsonValue {
+  if value.is_string() {
+    let text = value.as_str().unwrap_or_default();
+    let whole_name = text.strip_prefix("{{").and_then(|x| x.strip_suffix("}}")).map(str::trim);
+    if whole_name.is_some() && variables.contains_key(whole_name.unwrap()) {
+      return variables[whole_name.unwrap()].clone();
+    }
+    return substitute(text, variables).into();
+  }
+  if value.is_array() {
+    return value.members().map(|x| substitute_json(x, variables)).collect::<Vec<JsonValue>>().into();
+  }
+  if value.is_object() {
+    let mut output = JsonValue::new_object();
+    for (key, entry) in value.entries() {
+      output[key] = substitute_json(entry, variables);
+    }
+    return output;
+  }
+  return value.clone();
+}
+
+//Bodies and expectations are stored as json text, text that isnt valid json is substituted as plain text
+fn substitute_json_text(value: &str, variables: &Variables) -> String {
+  if !value.contains("{{") {
+    return value.to_string();
+  }
+  return match jzon::parse(value) {
+    Ok(x) => substitute_json(&x, variables).dump(),
+    Err(_) => substitute(value, variables),
+  };
+}
+
+fn substitute_map(map: &LinkedHashMap<String, String>, variables: &Variables) -> LinkedHashMap<String, String> {
+  return map.iter().map(|(k, v)| (k.clone(), substitute(v, variables))).collect();
+}
+
+//Returns a copy of the test with all variables in its request and expected outcome substituted
+pub fn resolve_test(test: &Test, variables: &Variables) -> Test {
+  let mut output = test.clone();
+  output.endpoint = substitute(&test.endpoint, variables);
+  output.body = test.body.as_ref().map(|x| substitute_json_text(x, variables));
+  output.cookies = test.cookies.as_ref().map(|x| substitute_map(x, variables));
+  output.headers = test.headers.as_ref().map(|x| substitute_map(x, variables));
+  output.expected_outcome = resolve_outcome(&test.expected_outcome, variables);
+  if test.retry.is_some() {
+    output.retry.as_mut().unwrap().until = test.retry.as_ref().unwrap().until.as_ref().map(|x| resolve_outcome(x, variables));
+  }
+
+  return output;
+}
+
+fn resolve_outcome(outcome: &TestOutcome, variables: &Variables) -> TestOutcome {
+  let mut output = outcome.clone();
+  output.body_equals = outcome.body_equals.as_ref().map(|x| substitute_json_text(x, variables));
+  output.body_contains = outcome.body_contains.as_ref().map(|x| substitute_json_text(x, variables));
+  output.headers_equal = outcome.headers_equal.as_ref().map(|x| substitute_map(x, variables));
+  output.headers_contain = outcome.headers_contain.as_ref().map(|x| substitute_map(x, variables));
+  if output.assertions.is_some() {
+    for assertion in output.assertions.as_mut().unwrap() {
+      assertion.value = assertion.value.as_ref().map(|x| substitute_json_text(x, variables));
+    }
+  }
+  return output;
+}
+
+pub fn resolve_task(task: &Task, variables: &Variables) -> Task {
+  let mut output = task.clone();
+  output.endpoint = substitute(&task.endpoint, variables);
+  output.body = task.body.as_ref().map(|x| substitute_json_text(x, variables));
+  output.headers = task.headers.as_ref().map(|x| substitute_map(x, variables));
+  return output;
+}
+
+//The variables setup and cleanup of a config can use, so the environments of test chains running at the same time with --jobs dont collide
+pub fn worker_variables(worker_id: usize, worker_port: usize) -> Variables {
+  return vec![
+    ("worker_id".to_string(), worker_id.into()),
+    ("worker_port".to_string(), worker_port.into()),
+  ].into_iter().collect();
+}
+
+//Returns a copy of the config with all variables in its commands, log file, api_hostname and finished_condition substituted
+pub fn resolve_config(config: &Config, variables: &Variables) -> Config {
+  let mut output = config.clone();
+  output.api_hostname = substitute(&config.api_hostname, variables);
+  output.setup.cmd = substitute(&config.setup.cmd, variables);
+  output.setup.background_cmd = substitute(&config.setup.background_cmd, variables);
+  output.setup.log_file = substitute(&config.setup.log_file, variables);
+  output.setup.finished_condition.conditions = config.setup.finished_condition.conditions.iter().map(|x| resolve_readiness_condition(x, variables)).collect();
+  output.cleanup.cmd = substitute(&config.cleanup.cmd, variables);
+  return output;
+}
+
+fn resolve_readiness_condition(condition: &ReadinessCondition, variables: &Variables) -> ReadinessCondition {
+  return match condition {
+    ReadinessCondition::EndpointReachable(endpoint) => ReadinessCondition::EndpointReachable(substitute(endpoint, variables)),
+    ReadinessCondition::EndpointStatus { endpoint, status_code } => ReadinessCondition::EndpointStatus { endpoint: substitute(endpoint, variables), status_code: *status_code },
+    ReadinessCondition::EndpointBodyContains { endpoint, text } => ReadinessCondition::EndpointBodyContains { endpoint: substitute(endpoint, variables), text: substitute(text, variables) },
+    ReadinessCondition::TcpPortOpen(address) => ReadinessCondition::TcpPortOpen(substitute(address, variables)),
+    ReadinessCondition::CommandSucceeds(cmd) => ReadinessCondition::CommandSucceeds(substitute(cmd, variables)),
+    ReadinessCondition::FileExists(path) => ReadinessCondition::FileExists(substitute(path, variables)),
+    ReadinessCondition::LogLineMatches(pattern) => ReadinessCondition::LogLineMatches(substitute(pattern, variables)),
+    ReadinessCondition::DelayMs(delay) => ReadinessCondition::DelayMs(*delay),
+    ReadinessCondition::All(conditions) => ReadinessCondition::All(conditions.iter().map(|x| resolve_readiness_condition(x, variables)).collect()),
+    ReadinessCondition::Any(conditions) => ReadinessCondition::Any(conditions.iter().map(|x| resolve_readiness_condition(x, variables)).collect()),
+  };
+}
+
+#[cfg(test)]
+mod unit_test {
+  use super::*;
+
+  fn variables() -> Variables {
+    return vec![
+      ("id".to_string(), 7.into()),
+      ("token".to_string(), "abc".into()),
+      ("user".to_string(), jzon::object! { name: "a" }),
+    ].into_iter().collect();
+  }
+
+  #[test]
+  fn substitute_replaces_known_variables() {
+    assert_eq!(substitute("/api/items/{{id}}?token={{ token }}", &variables()), "/api/items/7?token=abc");
+    assert_eq!(substitute("{{user}} {{unknown}} {{id", &variables()), r#"{"name":"a"} {{unknown}} {{id"#);
+  }
+
+  #[test]
+  fn substitute_json_keeps_types() {
+    let body = jzon::parse(r#"{"id":"{{id}}","label":"item {{id}}","owner":"{{user}}","other":"{{unknown}}"}"#).unwrap();
+    assert_eq!(substitute_json(&body, &variables()).dump(), r#"{"id":7,"label":"item 7","owner":{"name":"a"},"other":"{{unknown}}"}"#);
+  }
+
+  #[test]
+  fn capture_from_all_sources() {
+    let headers: LinkedHashMap<String, String> = vec![("location".to_string(), "/items/3".to_string())].into_iter().collect();
+    let cookies: LinkedHashMap<String, String> = vec![("session".to_string(), "xyz".to_string())].into_iter().collect();
+    let body = jzon::parse(r#"{"items":[{"id":3}]}"#).unwrap();
+    let response = CaptureResponse { status_code: 201, headers: &headers, cookies: &cookies, body: &body };
+    let captures: LinkedHashMap<String, String> = vec![
+      ("id".to_string(), "$.items[0].id".to_string()),
+      ("location".to_string(), "header:Location".to_string()),
+      ("session".to_string(), "cookie:session".to_string()),
+      ("status".to_string(), "status".to_string()),
+      ("missing".to_string(), "$.items[1].id".to_string()),
+    ].into_iter().collect();
+
+    let mut variables = Variables::new();
+    assert_eq!(capture(&captures, &response, &mut variables), vec!["couldnt capture missing, $.items[1].id had no value"]);
+    assert_eq!(variables.get("id"), Some(&3.into()));
+    assert_eq!(variables.get("location"), Some(&"/items/3".into()));
+    assert_eq!(variables.get("session"), Some(&"xyz".into()));
+    assert_eq!(variables.get("status"), Some(&201.into()));
+    assert_eq!(variables.get("missing"), None);
+  }
+
+  #[test]
+  fn resolve_config_substitutes_worker_variables() {
+    let mut config = Config {
+      name: "backend".to_string(),
+      description: String::new(),
+      setup: crate::SetupConfig {
+        cmd: String::new(),
+        background_cmd: "PORT={{worker_port}} cargo run".to_string(),
+        log_file: "/tmp/backend_{{worker_id}}.log".to_string(),
+        finished_condition: crate::SetupFinishedCondition {
+          conditions: vec![ReadinessCondition::Any(vec![ReadinessCondition::TcpPortOpen("{{worker_port}}".to_string()), ReadinessCondition::DelayMs(10)])],
+          timeout_ms: 300_000,
+          interval_ms: 100,
+          backoff: false,
+          max_interval_ms: 5000,
+        },
+      },
+      cleanup: crate::CleanupConfig { cmd: "docker rm -f db_{{worker_id}} {{token}}".to_string() },
+      api_hostname: "http://localhost:{{ worker_port }}".to_string(),
+      request_timeout_ms: None,
+    };
+    config = resolve_config(&config, &worker_variables(2, 4002));
+
+    assert_eq!(config.api_hostname, "http://localhost:4002");
+    assert_eq!(config.setup.background_cmd, "PORT=4002 cargo run");
+    assert_eq!(config.setup.log_file, "/tmp/backend_2.log");
+    assert_eq!(config.setup.finished_condition.conditions, vec![ReadinessCondition::Any(vec![ReadinessCondition::TcpPortOpen("4002".to_string()), ReadinessCondition::DelayMs(10)])]);
+    assert_eq!(config.cleanup.cmd, "docker rm -f db_2 {{token}}");
+  }
+}